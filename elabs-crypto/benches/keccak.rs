@@ -0,0 +1,42 @@
+// Copyright (C) 2022 The Elabs Authors.
+// This file is part of the Elabs.
+//
+// Elabs is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Elabs is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Elabs.  If not, see <https://www.gnu.org/licenses/>.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use elabs_crypto::{keccak256, keccak256_pairs};
+
+fn bench_keccak256_pairs(c: &mut Criterion) {
+    let pairs: Vec<([u8; 32], [u8; 32])> = (0..1024u32)
+        .map(|i| {
+            let a = keccak256(&i.to_be_bytes());
+            (a, keccak256(&a))
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("keccak256_pairs");
+    group.bench_function("naive", |b| {
+        b.iter(|| {
+            black_box(&pairs)
+                .iter()
+                .map(|(a, b)| keccak256(&[&a[..], &b[..]].concat()))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("pairs", |b| b.iter(|| keccak256_pairs(black_box(&pairs))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_keccak256_pairs);
+criterion_main!(benches);
@@ -0,0 +1,168 @@
+// Copyright (C) 2022 The Elabs Authors.
+// This file is part of the Elabs.
+//
+// Elabs is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Elabs is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Elabs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Contract ABI encoding of static types.
+
+use crate::*;
+
+/// Static ABI types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AbiType {
+    /// `uint256`.
+    Uint256,
+    /// `address`.
+    Address,
+    /// `bool`.
+    Bool,
+    /// `bytes32`.
+    Bytes32,
+}
+
+/// Values of the static ABI types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AbiValue {
+    /// Big endian `uint256`.
+    Uint256([u8; 32]),
+    /// `address`.
+    Address(Address),
+    /// `bool`.
+    Bool(bool),
+    /// `bytes32`.
+    Bytes32([u8; 32]),
+}
+
+impl AbiValue {
+    /// Create a `uint256` value from a u128.
+    pub fn uint(n: u128) -> Self {
+        let mut buf = [0u8; 32];
+        buf[16..].copy_from_slice(&n.to_be_bytes());
+        AbiValue::Uint256(buf)
+    }
+
+    fn matches(&self, ty: AbiType) -> bool {
+        matches!(
+            (self, ty),
+            (AbiValue::Uint256(_), AbiType::Uint256)
+                | (AbiValue::Address(_), AbiType::Address)
+                | (AbiValue::Bool(_), AbiType::Bool)
+                | (AbiValue::Bytes32(_), AbiType::Bytes32)
+        )
+    }
+
+    fn to_word(self) -> [u8; 32] {
+        let mut word = [0u8; 32];
+        match self {
+            AbiValue::Uint256(n) | AbiValue::Bytes32(n) => word = n,
+            AbiValue::Address(addr) => word[12..].copy_from_slice(&addr.0),
+            AbiValue::Bool(b) => word[31] = b as u8,
+        }
+        word
+    }
+}
+
+/// ABI encode values of static types.
+/// Each value is encoded as one 32 bytes word.
+/// # Arguments
+/// * `types` - types of the values.
+/// * `values` - values, in the same order as `types`.
+/// # Returns
+/// * `Ok(Vec<u8>)` - the encoded values.
+/// * `Err(String)` - if the values don't match the types.
+pub fn encode(types: &[AbiType], values: &[AbiValue]) -> Result<Vec<u8>, String> {
+    if types.len() != values.len() {
+        return Err(format!(
+            "expected {} values, got {}",
+            types.len(),
+            values.len()
+        ));
+    }
+
+    let mut out = Vec::with_capacity(32 * values.len());
+    for (i, (ty, value)) in types.iter().zip(values).enumerate() {
+        if !value.matches(*ty) {
+            return Err(format!("value {} is not of type {:?}", i, ty));
+        }
+        out.extend_from_slice(&value.to_word());
+    }
+    Ok(out)
+}
+
+/// ABI encode a function call.
+/// # Arguments
+/// * `signature` - canonical function signature, e.g. `transfer(address,uint256)`.
+/// * `types` - types of the arguments.
+/// * `values` - arguments, in the same order as `types`.
+/// # Returns
+/// * `Ok(Vec<u8>)` - the function selector followed by the encoded arguments.
+/// * `Err(String)` - if the values don't match the types.
+pub fn encode_with_selector(
+    signature: &str,
+    types: &[AbiType],
+    values: &[AbiValue],
+) -> Result<Vec<u8>, String> {
+    let mut out = function_selector(signature).to_vec();
+    out.extend(encode(types, values)?);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_abi_encode() {
+        // `baz(69, true)` example of the Solidity ABI spec.
+        let data = encode(
+            &[AbiType::Uint256, AbiType::Bool],
+            &[AbiValue::uint(69), AbiValue::Bool(true)],
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(data),
+            "0000000000000000000000000000000000000000000000000000000000000045\
+             0000000000000000000000000000000000000000000000000000000000000001"
+        );
+    }
+
+    #[test]
+    fn test_abi_encode_with_selector() {
+        let to = Address::from_hex("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").unwrap();
+        let data = encode_with_selector(
+            "transfer(address,uint256)",
+            &[AbiType::Address, AbiType::Uint256],
+            &[AbiValue::Address(to), AbiValue::uint(1_000_000)],
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(data),
+            "a9059cbb\
+             0000000000000000000000005aaeb6053f3e94c9b9a09f33669435e7ef1beaed\
+             00000000000000000000000000000000000000000000000000000000000f4240"
+        );
+    }
+
+    #[test]
+    fn test_abi_encode_bytes32() {
+        let data = encode(&[AbiType::Bytes32], &[AbiValue::Bytes32([0xab; 32])]).unwrap();
+        assert_eq!(data, [0xab; 32]);
+    }
+
+    #[test]
+    fn test_abi_encode_mismatch() {
+        assert!(encode(&[AbiType::Bool], &[]).is_err());
+        assert!(encode(&[AbiType::Bool], &[AbiValue::uint(1)]).is_err());
+    }
+}
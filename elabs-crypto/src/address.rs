@@ -0,0 +1,418 @@
+// Copyright (C) 2022 The Elabs Authors.
+// This file is part of the Elabs.
+//
+// Elabs is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Elabs is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Elabs.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Display;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    keccak256,
+    rlp::{self, RlpItem},
+    strip_hex_prefix, KeyError, PublicKey,
+};
+
+/// Ethereum address.
+/// The last 20 bytes of the keccak256 hash of the uncompressed public key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Address(pub [u8; 20]);
+
+const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
+
+impl Address {
+    /// Derive an Address from a PublicKey.
+    /// # Arguments
+    /// * `pk` - PublicKey.
+    /// # Returns
+    /// Address of the PublicKey.
+    pub fn from_public_key(pk: &PublicKey) -> Self {
+        let hash = keccak256(&pk.0[1..]);
+        let mut buf = [0u8; 20];
+        buf.copy_from_slice(&hash[12..]);
+        Address(buf)
+    }
+
+    /// Derive an Address from a 33 bytes compressed public key.
+    /// # Arguments
+    /// * `pk` - compressed public key.
+    /// # Returns
+    /// * `Ok(Address)` - Address of the public key.
+    /// * `Err(KeyError)` - if the bytes are not a valid compressed secp256k1 PublicKey.
+    pub fn from_compressed_public_key(pk: &[u8; 33]) -> Result<Self, KeyError> {
+        Ok(Address::from_public_key(&PublicKey::from_compressed(pk)?))
+    }
+
+    /// Return the address of a contract created with CREATE.
+    /// # Arguments
+    /// * `deployer` - address of the deployer.
+    /// * `nonce` - nonce of the deployer at deployment.
+    /// # Returns
+    /// keccak256(rlp([deployer, nonce]))[12..].
+    pub fn create(deployer: &Address, nonce: u64) -> Self {
+        let encoded = rlp::encode(&RlpItem::List(vec![
+            RlpItem::Bytes(deployer.0.to_vec()),
+            RlpItem::from_u64(nonce),
+        ]));
+        let hash = keccak256(&encoded);
+        let mut buf = [0u8; 20];
+        buf.copy_from_slice(&hash[12..]);
+        Address(buf)
+    }
+
+    /// Return the address of a contract created with CREATE2.
+    /// # Arguments
+    /// * `deployer` - address of the deployer.
+    /// * `salt` - salt given to CREATE2.
+    /// * `init_code` - creation code, including any constructor arguments.
+    /// # Returns
+    /// keccak256(0xff ++ deployer ++ salt ++ keccak256(init_code))[12..].
+    pub fn create2(deployer: &Address, salt: &[u8; 32], init_code: &[u8]) -> Self {
+        let mut buf = [0u8; 85];
+        buf[0] = 0xff;
+        buf[1..21].copy_from_slice(&deployer.0);
+        buf[21..53].copy_from_slice(salt);
+        buf[53..].copy_from_slice(&keccak256(init_code));
+        let hash = keccak256(&buf);
+        let mut addr = [0u8; 20];
+        addr.copy_from_slice(&hash[12..]);
+        Address(addr)
+    }
+
+    /// Create an Address from a slice.
+    /// # Arguments
+    /// * `buf` - slice.
+    /// # Returns
+    /// * `Ok(Address)` - if the slice is 20 bytes.
+    /// * `Err(KeyError)` - if the slice is not 20 bytes.
+    pub fn from_slice(buf: &[u8]) -> Result<Self, KeyError> {
+        if buf.len() != 20 {
+            return Err(KeyError::InvalidLength);
+        }
+        let mut addr = [0u8; 20];
+        addr.copy_from_slice(buf);
+        Ok(Address(addr))
+    }
+
+    /// Create an Address from a hex string.
+    /// If the string contains prefix `0x`, it will be removed.
+    /// The checksum casing is not validated.
+    /// # Arguments
+    /// * `hex` - hex string.
+    /// # Returns
+    /// * `Ok(Address)` - if the string is 20 bytes.
+    /// * `Err(KeyError)` - if the string is not 20 bytes or is not a valid hex string.
+    pub fn from_hex(hex: &str) -> Result<Self, KeyError> {
+        let hex_str = strip_hex_prefix(hex);
+
+        let mut buf = [0u8; 20];
+        hex::decode_to_slice(hex_str, &mut buf)?;
+        Ok(Address(buf))
+    }
+
+    /// Compare the Address with a hex string, ignoring the checksum casing.
+    /// # Arguments
+    /// * `s` - hex string, with or without `0x` prefix.
+    /// # Returns
+    /// Whether `s` is a valid address with the same bytes.
+    pub fn eq_str(&self, s: &str) -> bool {
+        Address::from_hex(s) == Ok(*self)
+    }
+
+    /// Check the EIP-55 checksum casing of an address hex string.
+    /// All-lowercase and all-uppercase strings carry no checksum and are accepted.
+    /// # Arguments
+    /// * `s` - hex string, with or without `0x` prefix.
+    /// # Returns
+    /// Whether `s` is a valid address whose casing matches its checksum.
+    pub fn is_valid_checksum(s: &str) -> bool {
+        let addr = match Address::from_hex(s) {
+            Ok(addr) => addr,
+            Err(_) => return false,
+        };
+        let hex_str = strip_hex_prefix(s);
+        if hex_str == hex_str.to_lowercase() || hex_str == hex_str.to_uppercase() {
+            return true;
+        }
+
+        let mut buf = [0u8; 42];
+        addr.write_checksum(&mut buf);
+        hex_str.as_bytes() == &buf[2..]
+    }
+
+    /// Return EIP-55 checksummed hex string of the Address, prefixed with `0x`.
+    /// # Returns
+    /// Checksummed hex string.
+    pub fn to_hex(&self) -> String {
+        let mut buf = [0u8; 42];
+        self.write_checksum(&mut buf);
+        String::from_utf8(buf.to_vec()).unwrap()
+    }
+
+    /// Return lowercase hex string of the Address, prefixed with `0x`.
+    /// # Returns
+    /// Lowercase hex string.
+    pub fn to_hex_lowercase(&self) -> String {
+        let mut buf = [0u8; 42];
+        self.write_hex(&mut buf);
+        String::from_utf8(buf.to_vec()).unwrap()
+    }
+
+    /// Return EIP-55 checksummed hex strings of many addresses.
+    /// A single scratch buffer is reused for all of the addresses.
+    /// # Arguments
+    /// * `addrs` - addresses.
+    /// # Returns
+    /// Checksummed hex strings, in the same order as `addrs`.
+    pub fn checksum_all(addrs: &[Address]) -> Vec<String> {
+        let mut buf = [0u8; 42];
+        addrs
+            .iter()
+            .map(|addr| {
+                addr.write_checksum(&mut buf);
+                String::from_utf8(buf.to_vec()).unwrap()
+            })
+            .collect()
+    }
+
+    // write the `0x` prefixed lowercase hex string into `buf`.
+    fn write_hex(&self, buf: &mut [u8; 42]) {
+        buf[0] = b'0';
+        buf[1] = b'x';
+        for (i, b) in self.0.iter().enumerate() {
+            buf[2 + i * 2] = HEX_CHARS[(b >> 4) as usize];
+            buf[3 + i * 2] = HEX_CHARS[(b & 0x0f) as usize];
+        }
+    }
+
+    // write the `0x` prefixed EIP-55 checksummed hex string into `buf`.
+    fn write_checksum(&self, buf: &mut [u8; 42]) {
+        self.write_hex(buf);
+
+        let hash = keccak256(&buf[2..]);
+        for i in 0..40 {
+            let nibble = if i % 2 == 0 {
+                hash[i / 2] >> 4
+            } else {
+                hash[i / 2] & 0x0f
+            };
+            if nibble >= 8 {
+                buf[2 + i] = buf[2 + i].to_ascii_uppercase();
+            }
+        }
+    }
+}
+
+impl Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Address::from_hex(&s).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PrivateKey;
+
+    #[test]
+    fn test_address_from_public_key() {
+        let sk = PrivateKey::from_hex(
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+        )
+        .unwrap();
+        let addr = Address::from_public_key(&sk.to_public().unwrap());
+        assert_eq!(addr.to_hex(), "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23");
+    }
+
+    #[test]
+    fn test_address_from_compressed_public_key() {
+        let pk = PrivateKey::random().to_public().unwrap();
+        let compressed = pk.to_compressed().unwrap();
+        assert_eq!(
+            Address::from_compressed_public_key(&compressed).unwrap(),
+            Address::from_public_key(&pk)
+        );
+        assert_eq!(
+            Address::from_compressed_public_key(&[0u8; 33]),
+            Err(KeyError::InvalidSecp256k1)
+        );
+    }
+
+    #[test]
+    fn test_address_create() {
+        let deployer = Address::from_hex("0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0").unwrap();
+        let expected = [
+            "0xcd234a471b72ba2f1ccf0a70fcaba648a5eecd8d",
+            "0x343c43a37d37dff08ae8c4a11544c718abb4fcf8",
+            "0xf778b86fa74e846c4f0a1fbd1335fe81c00a0c91",
+            "0xfffd933a0bc612844eaf0c6fe3e5b8e9b6c1d19c",
+        ];
+        for (nonce, addr) in expected.iter().enumerate() {
+            assert!(Address::create(&deployer, nonce as u64).eq_str(addr));
+        }
+    }
+
+    #[test]
+    fn test_address_create2() {
+        // Examples from EIP-1014.
+        let zero = Address([0u8; 20]);
+        let deadbeef = Address::from_hex("0xdeadbeef00000000000000000000000000000000").unwrap();
+        let mut feed = [0u8; 32];
+        feed[12..14].copy_from_slice(&[0xfe, 0xed]);
+        let cases: [(&Address, [u8; 32], &[u8], &str); 5] = [
+            (
+                &zero,
+                [0u8; 32],
+                &[0x00],
+                "0x4D1A2e2bB4F88F0250f26Ffff098B0b30B26BF38",
+            ),
+            (
+                &deadbeef,
+                [0u8; 32],
+                &[0x00],
+                "0xB928f69Bb1D91Cd65274e3c79d8986362984fDA3",
+            ),
+            (
+                &deadbeef,
+                feed,
+                &[0x00],
+                "0xD04116cDd17beBE565EB2422F2497E06cC1C9833",
+            ),
+            (
+                &zero,
+                [0u8; 32],
+                &[0xde, 0xad, 0xbe, 0xef],
+                "0x70f2b2914A2a4b783FaEFb75f459A580616Fcb5e",
+            ),
+            (
+                &zero,
+                [0u8; 32],
+                &[],
+                "0xE33C0C7F7df4809055C3ebA6c09CFe4BaF1BD9e0",
+            ),
+        ];
+        for (deployer, salt, init_code, expected) in cases {
+            assert_eq!(
+                Address::create2(deployer, &salt, init_code).to_hex(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_address_from_hex() {
+        let addr = Address::from_hex("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").unwrap();
+        let addr1 = Address::from_hex(&addr.to_hex()).unwrap();
+        assert_eq!(addr, addr1);
+        assert!(Address::from_hex("0x5aaeb6053f3e94c9b9a09f33669435e7ef1bea").is_err());
+        assert_eq!(
+            Address::from_hex("0X5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"),
+            Ok(addr)
+        );
+    }
+
+    #[test]
+    fn test_address_to_hex() {
+        let addr = Address::from_hex("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").unwrap();
+        assert_eq!(addr.to_hex(), "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+    }
+
+    #[test]
+    fn test_address_eq_str() {
+        let addr = Address::from_hex("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").unwrap();
+        assert!(addr.eq_str("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"));
+        assert!(addr.eq_str("5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED"));
+        assert!(addr.eq_str("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"));
+        assert!(!addr.eq_str("0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359"));
+        assert!(!addr.eq_str("not an address"));
+    }
+
+    #[test]
+    fn test_address_is_valid_checksum() {
+        assert!(Address::is_valid_checksum(
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        ));
+        assert!(Address::is_valid_checksum(
+            "5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        ));
+        assert!(Address::is_valid_checksum(
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+        ));
+        assert!(Address::is_valid_checksum(
+            "0x5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED"
+        ));
+        assert!(Address::is_valid_checksum(
+            "0X5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        ));
+        // first `A` flipped to lowercase.
+        assert!(!Address::is_valid_checksum(
+            "0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        ));
+        assert!(!Address::is_valid_checksum("0x5aAeb6053F3E94C9b9A09f3366"));
+    }
+
+    #[test]
+    fn test_address_to_hex_lowercase() {
+        let addr = Address::from_hex("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").unwrap();
+        assert_eq!(addr.to_hex(), "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+        assert_eq!(
+            addr.to_hex_lowercase(),
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+        );
+        assert!(addr.eq_str(&addr.to_hex_lowercase()));
+    }
+
+    #[test]
+    fn test_address_checksum_all() {
+        let hexes = [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ];
+        let addrs: Vec<Address> = hexes
+            .iter()
+            .map(|h| Address::from_hex(&h.to_lowercase()).unwrap())
+            .collect();
+        assert_eq!(Address::checksum_all(&addrs), hexes);
+    }
+
+    #[test]
+    fn test_address_ord() {
+        let a = Address([0x01; 20]);
+        let mut b = Address([0x01; 20]);
+        b.0[19] = 0x02;
+        let c = Address([0xff; 20]);
+        let mut addrs = vec![c, b, a];
+        addrs.sort();
+        assert_eq!(addrs, [a, b, c]);
+
+        let map: std::collections::BTreeMap<Address, u32> =
+            [(c, 3), (a, 1), (b, 2)].into_iter().collect();
+        assert_eq!(map.values().copied().collect::<Vec<_>>(), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright (C) 2022 The Elabs Authors.
+// This file is part of the Elabs.
+//
+// Elabs is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Elabs is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Elabs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! EIP-712 typed structured data hashing and signing.
+//! Struct fields are given already encoded (`encodeData`): atomic values
+//! padded to 32 bytes, and dynamic values or nested structs as their
+//! keccak256 / `hashStruct` hash.
+
+use secp256k1::{ecdsa::RecoverableSignature, Error};
+
+use crate::*;
+
+/// Typed data to be signed, as defined by EIP-712.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypedData {
+    /// The domain separator, `hashStruct(eip712Domain)`.
+    pub domain_separator: [u8; 32],
+    /// The type hash of the message, `keccak256(encodeType(type))`.
+    pub type_hash: [u8; 32],
+    /// The encoded fields of the message, in declaration order.
+    pub fields: Vec<[u8; 32]>,
+}
+
+impl TypedData {
+    /// Create a new TypedData.
+    /// # Arguments
+    /// * `domain_separator` - The domain separator.
+    /// * `type_hash` - The type hash of the message.
+    /// * `fields` - The encoded fields of the message.
+    /// # Returns
+    /// TypedData.
+    pub fn new(domain_separator: [u8; 32], type_hash: [u8; 32], fields: Vec<[u8; 32]>) -> Self {
+        TypedData {
+            domain_separator,
+            type_hash,
+            fields,
+        }
+    }
+
+    /// Return the message hash, `hashStruct(message)`.
+    pub fn message_hash(&self) -> [u8; 32] {
+        hash_struct(&self.type_hash, &self.fields)
+    }
+
+    /// Return the digest to be signed.
+    /// # Returns
+    /// keccak256(0x1901 ++ domainSeparator ++ hashStruct(message)).
+    pub fn digest(&self) -> [u8; 32] {
+        hash_typed_data(&self.domain_separator, &self.message_hash())
+    }
+}
+
+/// Return the type hash of the given encoded type.
+/// # Arguments
+/// * `encoded_type` - The encoded type, e.g. `Mail(address from,address to,string contents)`.
+/// # Returns
+/// keccak256 hash of the encoded type.
+pub fn type_hash(encoded_type: &str) -> [u8; 32] {
+    keccak256(encoded_type.as_bytes())
+}
+
+/// Return the hash of an encoded struct.
+/// # Arguments
+/// * `type_hash` - The type hash of the struct.
+/// * `fields` - The encoded fields of the struct.
+/// # Returns
+/// keccak256(typeHash ++ encodeData(struct)).
+pub fn hash_struct(type_hash: &[u8; 32], fields: &[[u8; 32]]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(type_hash);
+    for field in fields {
+        hasher.update(field);
+    }
+    hasher.finalize()
+}
+
+/// Return the EIP-712 digest of a struct hash in the given domain.
+/// # Arguments
+/// * `domain_separator` - The domain separator.
+/// * `struct_hash` - The hash of the message struct.
+/// # Returns
+/// keccak256(0x1901 ++ domainSeparator ++ structHash).
+pub fn hash_typed_data(domain_separator: &[u8; 32], struct_hash: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(&[0x19, 0x01]);
+    hasher.update(domain_separator);
+    hasher.update(struct_hash);
+    hasher.finalize()
+}
+
+/// Sign typed data with the given private key.
+/// # Arguments
+/// * `data` - The typed data.
+/// * `private_key` - The private key.
+/// # Returns
+/// * `Ok(Signature)` - The signature.
+/// * `Err(Error)` - The error that occurred.
+pub fn sign_typed_data(
+    data: &TypedData,
+    private_key: PrivateKey,
+) -> Result<RecoverableSignature, Error> {
+    sign_hash(&data.digest(), private_key)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn string(s: &str) -> [u8; 32] {
+        keccak256(s.as_bytes())
+    }
+
+    fn address(s: &str) -> [u8; 32] {
+        let mut buf = [0u8; 32];
+        buf[12..].copy_from_slice(&Address::from_hex(s).unwrap().0);
+        buf
+    }
+
+    fn uint(n: u64) -> [u8; 32] {
+        let mut buf = [0u8; 32];
+        buf[24..].copy_from_slice(&n.to_be_bytes());
+        buf
+    }
+
+    // The `Mail` example from the EIP-712 specification.
+    fn mail() -> TypedData {
+        let domain_separator = hash_struct(
+            &type_hash(
+                "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
+            ),
+            &[
+                string("Ether Mail"),
+                string("1"),
+                uint(1),
+                address("0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"),
+            ],
+        );
+        let person = type_hash("Person(string name,address wallet)");
+        let from = hash_struct(
+            &person,
+            &[
+                string("Cow"),
+                address("0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"),
+            ],
+        );
+        let to = hash_struct(
+            &person,
+            &[
+                string("Bob"),
+                address("0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"),
+            ],
+        );
+        TypedData::new(
+            domain_separator,
+            type_hash(
+                "Mail(Person from,Person to,string contents)Person(string name,address wallet)",
+            ),
+            vec![from, to, string("Hello, Bob!")],
+        )
+    }
+
+    #[test]
+    fn test_typed_data_hashes() {
+        let data = mail();
+        assert_eq!(
+            hex::encode(data.domain_separator),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+        assert_eq!(
+            hex::encode(data.message_hash()),
+            "c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"
+        );
+        assert_eq!(
+            hex::encode(data.digest()),
+            "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+        );
+    }
+
+    #[test]
+    fn test_sign_typed_data() {
+        let sk = PrivateKey::from_slice(&keccak256(b"cow")).unwrap();
+        let (recid, bsig) = sign_typed_data(&mail(), sk).unwrap().serialize_compact();
+        assert_eq!(
+            hex::encode(bsig),
+            "4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d\
+             07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562"
+        );
+        assert_eq!(recid.to_i32() + 27, 28);
+    }
+}
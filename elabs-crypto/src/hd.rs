@@ -0,0 +1,192 @@
+// Copyright (C) 2022 The Elabs Authors.
+// This file is part of the Elabs.
+//
+// Elabs is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Elabs is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Elabs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! BIP-32 hierarchical deterministic wallets.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+
+use crate::*;
+
+/// The BIP-44 derivation path of the first Ethereum account.
+pub const ETHEREUM_PATH: &str = "m/44'/60'/0'/0/0";
+
+const HARDENED: u32 = 0x8000_0000;
+
+/// BIP-32 extended private key.
+/// Created from a seed, it derives child keys along a derivation path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HdWallet {
+    key: PrivateKey,
+    chain_code: [u8; 32],
+}
+
+impl HdWallet {
+    /// Create the master HdWallet of a seed.
+    /// # Arguments
+    /// * `seed` - seed bytes, usually 64 bytes derived from a mnemonic.
+    /// # Returns
+    /// * `Ok(HdWallet)` - the master wallet.
+    /// * `Err(KeyError)` - if the master key is not a valid secp256k1 SecretKey.
+    pub fn from_seed(seed: &[u8]) -> Result<Self, KeyError> {
+        let (key, chain_code) = hmac_sha512(b"Bitcoin seed", &[seed]);
+        Ok(HdWallet {
+            key: PrivateKey::from_slice(&key)?,
+            chain_code,
+        })
+    }
+
+    /// Create the master HdWallet of a BIP-39 mnemonic phrase.
+    /// The phrase and passphrase are NFKD normalized, as BIP-39 requires.
+    /// # Arguments
+    /// * `phrase` - mnemonic phrase (english wordlist).
+    /// * `passphrase` - optional passphrase, use `""` for none.
+    /// # Returns
+    /// * `Ok(HdWallet)` - the master wallet.
+    /// * `Err(KeyError)` - if the phrase has unknown words, a bad length or a bad checksum.
+    pub fn from_mnemonic(phrase: &str, passphrase: &str) -> Result<Self, KeyError> {
+        let mnemonic = bip39::Mnemonic::parse(phrase).map_err(|_| KeyError::InvalidMnemonic)?;
+        HdWallet::from_seed(&mnemonic.to_seed(passphrase))
+    }
+
+    /// Return the private key of this wallet.
+    pub fn private_key(&self) -> PrivateKey {
+        self.key.clone()
+    }
+
+    /// Derive the private key at the given path.
+    /// Hardened components are marked with `'` or `h`, e.g. [`ETHEREUM_PATH`].
+    /// # Arguments
+    /// * `path` - derivation path starting with `m`.
+    /// # Returns
+    /// * `Ok(PrivateKey)` - the derived key.
+    /// * `Err(KeyError)` - if the path is malformed.
+    pub fn derive(&self, path: &str) -> Result<PrivateKey, KeyError> {
+        let mut parts = path.split('/');
+        if parts.next() != Some("m") {
+            return Err(KeyError::InvalidPath);
+        }
+
+        let mut wallet = self.clone();
+        for part in parts {
+            let (index, hardened) = match part.strip_suffix(['\'', 'h']) {
+                Some(index) => (index, true),
+                None => (part, false),
+            };
+            let index: u32 = index.parse().map_err(|_| KeyError::InvalidPath)?;
+            if index >= HARDENED {
+                return Err(KeyError::InvalidPath);
+            }
+            wallet = wallet.child(if hardened { index | HARDENED } else { index })?;
+        }
+        Ok(wallet.key)
+    }
+
+    /// Derive the private key of the Ethereum account at the given index.
+    /// # Arguments
+    /// * `index` - account index, the last component of `m/44'/60'/0'/0/{index}`.
+    /// # Returns
+    /// * `Ok(PrivateKey)` - the derived key.
+    /// * `Err(KeyError)` - if the index is out of range.
+    pub fn derive_account(&self, index: u32) -> Result<PrivateKey, KeyError> {
+        self.derive(&format!("m/44'/60'/0'/0/{}", index))
+    }
+
+    fn child(&self, index: u32) -> Result<Self, KeyError> {
+        let index_bytes = index.to_be_bytes();
+        let (tweak, chain_code) = if index >= HARDENED {
+            hmac_sha512(&self.chain_code, &[&[0u8], &self.key.0, &index_bytes])
+        } else {
+            let pk = self.key.to_public()?.to_secp256k1()?.serialize();
+            hmac_sha512(&self.chain_code, &[&pk, &index_bytes])
+        };
+
+        let mut sk = self.key.to_secp256k1()?;
+        sk.add_assign(&tweak)
+            .map_err(|_| KeyError::InvalidSecp256k1)?;
+        Ok(HdWallet {
+            key: PrivateKey::from_secp256k1(sk),
+            chain_code,
+        })
+    }
+}
+
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> ([u8; 32], [u8; 32]) {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).unwrap();
+    for d in data {
+        mac.update(d);
+    }
+    let out = mac.finalize().into_bytes();
+    let mut left = [0u8; 32];
+    let mut right = [0u8; 32];
+    left.copy_from_slice(&out[..32]);
+    right.copy_from_slice(&out[32..]);
+    (left, right)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PHRASE: &str = "test test test test test test test test test test test junk";
+
+    fn address(sk: &PrivateKey) -> String {
+        Address::from_public_key(&sk.to_public().unwrap()).to_hex()
+    }
+
+    #[test]
+    fn test_hd_wallet_derive() {
+        let wallet = HdWallet::from_mnemonic(PHRASE, "").unwrap();
+        let sk = wallet.derive(ETHEREUM_PATH).unwrap();
+        assert_eq!(address(&sk), "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+        assert_eq!(sk, wallet.derive("m/44h/60h/0h/0/0").unwrap());
+    }
+
+    #[test]
+    fn test_hd_wallet_derive_account() {
+        let wallet = HdWallet::from_mnemonic(PHRASE, "").unwrap();
+        assert_eq!(
+            wallet.derive_account(0).unwrap(),
+            wallet.derive(ETHEREUM_PATH).unwrap()
+        );
+        assert_eq!(
+            address(&wallet.derive_account(1).unwrap()),
+            "0x70997970C51812dc3A010C7d01b50e0d17dc79C8"
+        );
+    }
+
+    #[test]
+    fn test_hd_wallet_unicode_passphrase() {
+        let nfc = HdWallet::from_mnemonic(PHRASE, "caf\u{e9}").unwrap();
+        let nfd = HdWallet::from_mnemonic(PHRASE, "cafe\u{301}").unwrap();
+        assert_eq!(nfc, nfd);
+        assert_ne!(nfc, HdWallet::from_mnemonic(PHRASE, "cafe").unwrap());
+    }
+
+    #[test]
+    fn test_hd_wallet_master() {
+        let wallet = HdWallet::from_mnemonic(PHRASE, "").unwrap();
+        assert_eq!(wallet.derive("m").unwrap(), wallet.private_key());
+    }
+
+    #[test]
+    fn test_hd_wallet_invalid_path() {
+        let wallet = HdWallet::from_mnemonic(PHRASE, "").unwrap();
+        for path in ["", "44'/60'", "m/x", "m/2147483648", "m//0"] {
+            assert_eq!(wallet.derive(path), Err(KeyError::InvalidPath));
+        }
+    }
+}
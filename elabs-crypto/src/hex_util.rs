@@ -0,0 +1,81 @@
+// Copyright (C) 2022 The Elabs Authors.
+// This file is part of the Elabs.
+//
+// Elabs is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Elabs is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Elabs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Hex encoding helpers.
+//!
+//! Hex strings produced by this crate are always prefixed with `0x`,
+//! and hex strings are accepted with or without the prefix.
+
+use crate::KeyError;
+
+/// Encode bytes as a `0x` prefixed lowercase hex string.
+/// # Arguments
+/// * `bytes` - bytes to encode.
+/// # Returns
+/// `0x` prefixed hex string.
+pub fn to_hex_prefixed(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Strip an optional `0x` or `0X` prefix from a hex string.
+/// # Arguments
+/// * `s` - hex string.
+/// # Returns
+/// The string without its prefix.
+pub fn strip_hex_prefix(s: &str) -> &str {
+    s.strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s)
+}
+
+/// Decode a hex string, with or without `0x` prefix.
+/// # Arguments
+/// * `s` - hex string.
+/// # Returns
+/// * `Ok(Vec<u8>)` - decoded bytes.
+/// * `Err(KeyError)` - if the string is not valid hex.
+pub fn from_hex_any(s: &str) -> Result<Vec<u8>, KeyError> {
+    Ok(hex::decode(strip_hex_prefix(s))?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_to_hex_prefixed() {
+        assert_eq!(to_hex_prefixed(&[]), "0x");
+        assert_eq!(to_hex_prefixed(&[0xde, 0xad, 0xbe, 0xef]), "0xdeadbeef");
+    }
+
+    #[test]
+    fn test_strip_hex_prefix() {
+        assert_eq!(strip_hex_prefix("0xab"), "ab");
+        assert_eq!(strip_hex_prefix("0Xab"), "ab");
+        assert_eq!(strip_hex_prefix("ab"), "ab");
+        assert_eq!(strip_hex_prefix("0x0xab"), "0xab");
+    }
+
+    #[test]
+    fn test_from_hex_any() {
+        let bytes = vec![0xde, 0xad, 0xbe, 0xef];
+        assert_eq!(from_hex_any("0xdeadbeef"), Ok(bytes.clone()));
+        assert_eq!(from_hex_any("0XDEADBEEF"), Ok(bytes.clone()));
+        assert_eq!(from_hex_any("deadbeef"), Ok(bytes));
+        assert_eq!(from_hex_any("0xdeadbee"), Err(KeyError::OddLength));
+        assert_eq!(from_hex_any("0xzz"), Err(KeyError::InvalidHexChar));
+    }
+}
@@ -17,21 +17,24 @@
 /// ecdsa(secp256k1) SecretKey wrapper.
 /// This is a wrapper for secp256k1 SecretKey.
 /// The default byte size is 32 bytes.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The bytes are zeroized when the PrivateKey is dropped, so it is not `Copy`;
+/// use `clone` explicitly when a second copy is needed.
+#[derive(Clone)]
 pub struct PrivateKey(pub [u8; 32]);
 
 /// ecdsa(secp256k1) PublicKey wrapper.
 /// This is a wrapper for secp256k1 PublicKey.
 /// The default byte size is 65 bytes.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug)]
 pub struct PublicKey(pub [u8; 65]);
 
-// TODO: implement Eq for PrivateKey and PublicKey.
-// TODO: implement PartialEq for PrivateKey and PublicKey.
-
-use std::fmt::Display;
+use std::fmt::{Debug, Display};
 
 use rand::RngCore;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::{strip_hex_prefix, to_hex_prefixed, Address, HdWallet};
 
 impl PrivateKey {
     /// Generate a random PrivateKey.
@@ -54,6 +57,9 @@ impl PrivateKey {
     }
 
     /// Create a PrivateKey from a slice.
+    /// The bytes are validated through a `secp256k1::SecretKey` temporary, which is
+    /// `Copy` and cannot be wiped with secp256k1 0.21, so a copy of the key may remain
+    /// on the stack. The returned PrivateKey is zeroized on drop.
     /// # Arguments
     /// * `buf` - slice.
     /// # Returns
@@ -63,33 +69,44 @@ impl PrivateKey {
         if buf.len() != 32 {
             return Err(KeyError::InvalidLength);
         }
-        let sk = secp256k1::SecretKey::from_slice(buf).map_err(|_| KeyError::InvalidSecp256k1)?;
-        Ok(PrivateKey(sk.serialize_secret()))
+        secp256k1::SecretKey::from_slice(buf).map_err(|_| KeyError::InvalidSecp256k1)?;
+        let mut key = [0u8; 32];
+        key.copy_from_slice(buf);
+        Ok(PrivateKey(key))
     }
 
     /// Create a PrivateKey from a hex string.
     /// If the string contains prefix `0x`, it will be removed.
+    /// Only the length is checked up front; the digits themselves are decoded
+    /// without secret-dependent branches and a bad digit is reported once at the end.
     /// # Arguments
     /// * `hex` - hex string.
     /// # Returns
     /// * `Ok(PrivateKey)` - if the string is 32 bytes and is a valid secp256k1 SecretKey.
     /// * `Err(KeyError)` - if the string is not 32 bytes or is not a valid secp256k1 SecretKey.
     pub fn from_hex(hex: &str) -> Result<Self, KeyError> {
-        let hex_str = if hex.starts_with("0x") {
-            &hex[2..]
-        } else {
-            hex
-        };
-
-        if hex_str.len() != 64 {
-            return Err(KeyError::InvalidLength);
-        }
+        let hex_str = strip_hex_prefix(hex);
 
         let mut buf = [0u8; 32];
-        hex::decode_to_slice(hex_str, &mut buf).map_err(|_| KeyError::InvalidHex)?;
+        let decoded = decode_secret_hex(hex_str.as_bytes(), &mut buf);
+        let sk = decoded.and_then(|_| {
+            secp256k1::SecretKey::from_slice(&buf).map_err(|_| KeyError::InvalidSecp256k1)
+        });
+        buf.zeroize();
+        Ok(PrivateKey(sk?.serialize_secret()))
+    }
 
-        let sk = secp256k1::SecretKey::from_slice(&buf).map_err(|_| KeyError::InvalidSecp256k1)?;
-        Ok(PrivateKey(sk.serialize_secret()))
+    /// Create a PrivateKey from a BIP-39 mnemonic phrase.
+    /// The phrase is turned into a seed per BIP-39, and the BIP-32 master key
+    /// secret of that seed is returned.
+    /// # Arguments
+    /// * `phrase` - mnemonic phrase (english wordlist).
+    /// * `passphrase` - optional passphrase, use `""` for none.
+    /// # Returns
+    /// * `Ok(PrivateKey)` - the master key.
+    /// * `Err(KeyError)` - if the phrase has unknown words, a bad length or a bad checksum.
+    pub fn from_mnemonic(phrase: &str, passphrase: &str) -> Result<Self, KeyError> {
+        Ok(HdWallet::from_mnemonic(phrase, passphrase)?.private_key())
     }
 
     /// Return the secp256k1 SecretKey.
@@ -109,13 +126,13 @@ impl PrivateKey {
         Ok(sk.serialize_secret())
     }
 
-    /// Return hex string of the PrivateKey.
+    /// Return hex string of the PrivateKey, prefixed with `0x`.
     /// # Returns
     /// * `String` - if the PrivateKey is a valid secp256k1 SecretKey.
     /// * `Err(KeyError)` - if the PrivateKey is not a valid secp256k1 SecretKey.
     pub fn to_hex(&self) -> Result<String, KeyError> {
         let sk = self.to_secp256k1()?;
-        Ok(hex::encode(sk.serialize_secret()))
+        Ok(to_hex_prefixed(&sk.serialize_secret()))
     }
 
     /// Return the secp256k1 PublicKey.
@@ -129,6 +146,53 @@ impl PrivateKey {
                 .serialize_uncompressed(),
         ))
     }
+
+    /// Return the Address of the PrivateKey.
+    /// # Returns
+    /// * `Ok(Address)` - the Address.
+    /// * `Err(KeyError)` - if the PrivateKey is not a valid secp256k1 SecretKey.
+    pub fn address(&self) -> Result<Address, KeyError> {
+        Ok(Address::from_public_key(&self.to_public()?))
+    }
+}
+
+// compare in constant time, so the comparison does not leak where the keys differ.
+impl PartialEq for PrivateKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.ct_eq(&other.0).into()
+    }
+}
+
+impl Eq for PrivateKey {}
+
+// compare the secp256k1 points, so different encodings of the same key are equal.
+impl PartialEq for PublicKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.to_secp256k1(), other.to_secp256k1()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => self.0 == other.0,
+        }
+    }
+}
+
+impl Eq for PublicKey {}
+
+impl Debug for PrivateKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "PrivateKey(***)")
+    }
+}
+
+impl Zeroize for PrivateKey {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for PrivateKey {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
 }
 
 impl PublicKey {
@@ -177,19 +241,26 @@ impl PublicKey {
     /// * `Ok(PublicKey)` - if the string is 65 bytes and is a valid secp256k1 PublicKey.
     /// * `Err(KeyError)` - if the string is not 65 bytes or is not a valid secp256k1 PublicKey.
     pub fn from_hex(hex: &str) -> Result<Self, KeyError> {
-        let hex_str = if hex.starts_with("0x") {
-            &hex[2..]
-        } else {
-            hex
-        };
+        let hex_str = strip_hex_prefix(hex);
 
         let mut buf = [0u8; 65];
-        hex::decode_to_slice(hex_str, &mut buf).map_err(|_| KeyError::InvalidHex)?;
+        hex::decode_to_slice(hex_str, &mut buf)?;
 
         let pk = secp256k1::PublicKey::from_slice(&buf).map_err(|_| KeyError::InvalidSecp256k1)?;
         Ok(PublicKey(pk.serialize_uncompressed()))
     }
 
+    /// Create a PublicKey from its 33 bytes compressed encoding.
+    /// # Arguments
+    /// * `buf` - compressed public key.
+    /// # Returns
+    /// * `Ok(PublicKey)` - if the bytes are a valid compressed secp256k1 PublicKey.
+    /// * `Err(KeyError)` - if the bytes are not a valid compressed secp256k1 PublicKey.
+    pub fn from_compressed(buf: &[u8; 33]) -> Result<Self, KeyError> {
+        let pk = secp256k1::PublicKey::from_slice(buf).map_err(|_| KeyError::InvalidSecp256k1)?;
+        Ok(PublicKey(pk.serialize_uncompressed()))
+    }
+
     /// Return the secp256k1 PublicKey.
     /// # Returns
     /// * `Ok(secp256k1::PublicKey)` - if the PublicKey is a valid secp256k1 PublicKey.
@@ -207,13 +278,22 @@ impl PublicKey {
         Ok(pk.serialize_uncompressed())
     }
 
-    /// Return hex string of the PublicKey.
+    /// Return hex string of the PublicKey, prefixed with `0x`.
     /// # Returns
     /// * `String` - if the PublicKey is a valid secp256k1 PublicKey.
     /// * `Err(KeyError)` - if the PublicKey is not a valid secp256k1 PublicKey.
     pub fn to_hex(&self) -> Result<String, KeyError> {
         let pk = self.to_secp256k1()?;
-        Ok(hex::encode(pk.serialize_uncompressed()))
+        Ok(to_hex_prefixed(&pk.serialize_uncompressed()))
+    }
+
+    /// Return the 33 bytes compressed encoding of the PublicKey.
+    /// # Returns
+    /// * `[u8; 33]` - if the PublicKey is a valid secp256k1 PublicKey.
+    /// * `Err(KeyError)` - if the PublicKey is not a valid secp256k1 PublicKey.
+    pub fn to_compressed(&self) -> Result<[u8; 33], KeyError> {
+        let pk = self.to_secp256k1()?;
+        Ok(pk.serialize())
     }
 }
 
@@ -224,8 +304,18 @@ pub enum KeyError {
     InvalidLength,
     /// Invalid secp256k1 SecretKey.
     InvalidSecp256k1,
-    /// Invalid hex string.
-    InvalidHex,
+    /// Hex string with an odd number of digits.
+    OddLength,
+    /// Hex string with a non hex character.
+    InvalidHexChar,
+    /// Invalid BIP-39 mnemonic.
+    InvalidMnemonic,
+    /// Invalid BIP-32 derivation path.
+    InvalidPath,
+    /// Wrong keystore password (MAC mismatch).
+    InvalidPassword,
+    /// Malformed or unsupported keystore.
+    InvalidKeystore,
 }
 
 impl Display for KeyError {
@@ -233,11 +323,71 @@ impl Display for KeyError {
         match self {
             KeyError::InvalidLength => write!(f, "Invalid length"),
             KeyError::InvalidSecp256k1 => write!(f, "Invalid secp256k1 SecretKey"),
-            KeyError::InvalidHex => write!(f, "Invalid hex string"),
+            KeyError::OddLength => write!(f, "Odd number of hex digits"),
+            KeyError::InvalidHexChar => write!(f, "Invalid hex character"),
+            KeyError::InvalidMnemonic => write!(f, "Invalid mnemonic"),
+            KeyError::InvalidPath => write!(f, "Invalid derivation path"),
+            KeyError::InvalidPassword => write!(f, "Invalid keystore password"),
+            KeyError::InvalidKeystore => write!(f, "Invalid keystore"),
         }
     }
 }
 
+impl From<hex::FromHexError> for KeyError {
+    fn from(err: hex::FromHexError) -> Self {
+        match err {
+            hex::FromHexError::OddLength => KeyError::OddLength,
+            hex::FromHexError::InvalidStringLength => KeyError::InvalidLength,
+            hex::FromHexError::InvalidHexCharacter { .. } => KeyError::InvalidHexChar,
+        }
+    }
+}
+
+/// Decode a single hex digit without branching on its value.
+/// # Arguments
+/// * `c` - ASCII hex digit.
+/// # Returns
+/// * `(value, mask)` - the nibble and `-1` if `c` is a hex digit, `0` otherwise.
+fn decode_nibble(c: u8) -> (u8, i16) {
+    let c = c as i16;
+    let lower = c | 0x20;
+    // (lo - 1 - x) & (x - hi - 1) has its sign bit set only for lo <= x <= hi.
+    let is_digit = ((0x2f - c) & (c - 0x3a)) >> 8;
+    let is_alpha = ((0x60 - lower) & (lower - 0x67)) >> 8;
+    let value = (is_digit & (c - 0x30)) | (is_alpha & (lower - 0x57));
+    (value as u8, is_digit | is_alpha)
+}
+
+/// Decode 64 hex digits into a 32 byte secret.
+/// Every digit is processed, invalid ones are only reported after the loop.
+/// # Arguments
+/// * `hex` - hex digits without prefix.
+/// * `out` - output buffer.
+/// # Returns
+/// * `Ok(())` - if `hex` is 64 valid hex digits.
+/// * `Err(KeyError)` - if the length is wrong or a digit is invalid.
+fn decode_secret_hex(hex: &[u8], out: &mut [u8; 32]) -> Result<(), KeyError> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(KeyError::OddLength);
+    }
+    if hex.len() != 64 {
+        return Err(KeyError::InvalidLength);
+    }
+
+    let mut valid = -1i16;
+    for (byte, pair) in out.iter_mut().zip(hex.chunks_exact(2)) {
+        let (hi, hi_ok) = decode_nibble(pair[0]);
+        let (lo, lo_ok) = decode_nibble(pair[1]);
+        *byte = (hi << 4) | lo;
+        valid &= hi_ok & lo_ok;
+    }
+
+    if valid == 0 {
+        return Err(KeyError::InvalidHexChar);
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -276,6 +426,105 @@ mod test {
         let sk1 = PrivateKey::from_hex(&sk.to_hex().unwrap()).unwrap();
         assert_eq!(sk.0.len(), 32);
         assert_eq!(sk, sk1);
+        let sk2 = PrivateKey::from_hex(&sk.to_hex().unwrap().replacen("0x", "0X", 1)).unwrap();
+        assert_eq!(sk, sk2);
+    }
+
+    #[test]
+    fn test_decode_nibble() {
+        for c in 0..=255u8 {
+            let (value, mask) = decode_nibble(c);
+            match (c as char).to_digit(16) {
+                Some(d) => assert_eq!((value, mask), (d as u8, -1)),
+                None => assert_eq!(mask, 0, "{:#x}", c),
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_hex_errors() {
+        let hex = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+        assert_eq!(
+            PrivateKey::from_hex(&hex[1..]).unwrap_err(),
+            KeyError::OddLength
+        );
+        assert_eq!(
+            PrivateKey::from_hex(&hex[2..]).unwrap_err(),
+            KeyError::InvalidLength
+        );
+        assert_eq!(
+            PrivateKey::from_hex(&hex.replace('a', "g")).unwrap_err(),
+            KeyError::InvalidHexChar
+        );
+
+        assert_eq!(
+            PrivateKey::from_hex(&format!("{}\u{e9}", &hex[..62])).unwrap_err(),
+            KeyError::InvalidHexChar
+        );
+        assert_eq!(
+            PrivateKey::from_hex(&hex.to_uppercase()).unwrap(),
+            PrivateKey::from_hex(hex).unwrap()
+        );
+
+        let pk = PrivateKey::from_hex(hex).unwrap().to_public().unwrap();
+        let pk_hex = pk.to_hex().unwrap();
+        assert_eq!(PublicKey::from_hex(&pk_hex).unwrap(), pk);
+        assert_eq!(PublicKey::from_hex(&pk_hex[2..]).unwrap(), pk);
+        assert_eq!(
+            PublicKey::from_hex(&pk_hex[3..]).unwrap_err(),
+            KeyError::OddLength
+        );
+    }
+
+    #[test]
+    fn test_private_key_from_mnemonic() {
+        let phrase = "abandon abandon abandon abandon abandon abandon \
+                      abandon abandon abandon abandon abandon about";
+        let sk = PrivateKey::from_mnemonic(phrase, "").unwrap();
+        let sk1 = PrivateKey::from_mnemonic(phrase, "").unwrap();
+        let sk2 = PrivateKey::from_mnemonic(phrase, "TREZOR").unwrap();
+        assert_eq!(sk, sk1);
+        assert_ne!(sk, sk2);
+        // BIP-39 test vector, master key of xprv9s21ZrQH143K3h3fDYiay8moc...
+        assert_eq!(
+            sk2.to_hex().unwrap(),
+            "0xcbedc75b0d6412c85c79bc13875112ef912fd1e756631b5a00330866f22ff184"
+        );
+
+        let bad_checksum = "abandon abandon abandon abandon abandon abandon \
+                            abandon abandon abandon abandon abandon abandon";
+        assert_eq!(
+            PrivateKey::from_mnemonic(bad_checksum, ""),
+            Err(KeyError::InvalidMnemonic)
+        );
+        assert_eq!(
+            PrivateKey::from_mnemonic("not a mnemonic", ""),
+            Err(KeyError::InvalidMnemonic)
+        );
+    }
+
+    #[test]
+    fn test_private_key_eq() {
+        let sk = PrivateKey::random();
+        let sk1 = PrivateKey::from_slice(&sk.0).unwrap();
+        let mut buf = sk.0;
+        buf[31] ^= 1;
+        let sk2 = PrivateKey(buf);
+        assert_eq!(sk, sk1);
+        assert_ne!(sk, sk2);
+    }
+
+    #[test]
+    fn test_private_key_debug() {
+        let sk = PrivateKey::random();
+        assert_eq!(format!("{:?}", sk), "PrivateKey(***)");
+    }
+
+    #[test]
+    fn test_private_key_zeroize() {
+        let mut sk = PrivateKey::random();
+        sk.zeroize();
+        assert_eq!(sk.0, [0u8; 32]);
     }
 
     #[test]
@@ -296,7 +545,8 @@ mod test {
     fn test_private_key_to_hex() {
         let sk = PrivateKey::random();
         let sk = sk.to_hex().unwrap();
-        assert_eq!(sk.len(), 64);
+        assert!(sk.starts_with("0x"));
+        assert_eq!(sk.len(), 66);
     }
 
     #[test]
@@ -306,6 +556,18 @@ mod test {
         assert_eq!(pk.0.len(), 65);
     }
 
+    #[test]
+    fn test_private_key_address() {
+        let sk = PrivateKey::from_hex(
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+        )
+        .unwrap();
+        assert_eq!(
+            sk.address().unwrap().to_hex(),
+            "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23"
+        );
+    }
+
     #[test]
     fn test_public_key_from_secp256k1() {
         let sk = PrivateKey::random();
@@ -330,6 +592,8 @@ mod test {
         let pk1 = PublicKey::from_hex(&pk.to_hex().unwrap()).unwrap();
         assert_eq!(pk1.0.len(), 65);
         assert_eq!(pk, pk1);
+        let pk2 = PublicKey::from_hex(&pk.to_hex().unwrap().replacen("0x", "0X", 1)).unwrap();
+        assert_eq!(pk, pk2);
     }
 
     #[test]
@@ -350,6 +614,36 @@ mod test {
     fn test_public_key_to_hex() {
         let sk = PrivateKey::random();
         let pk = sk.to_public().unwrap().to_hex().unwrap();
-        assert_eq!(pk.len(), 130);
+        assert!(pk.starts_with("0x"));
+        assert_eq!(pk.len(), 132);
+    }
+
+    #[test]
+    fn test_public_key_compressed() {
+        let sk = PrivateKey::random();
+        let pk = sk.to_public().unwrap();
+        let compressed = pk.to_compressed().unwrap();
+        assert!(compressed[0] == 0x02 || compressed[0] == 0x03);
+        let pk1 = PublicKey::from_compressed(&compressed).unwrap();
+        assert_eq!(pk.to_bytes().unwrap(), pk1.to_bytes().unwrap());
+        assert_eq!(
+            PublicKey::from_compressed(&[0u8; 33]),
+            Err(KeyError::InvalidSecp256k1)
+        );
+    }
+
+    #[test]
+    fn test_public_key_eq_encoding() {
+        let pk = PrivateKey::random().to_public().unwrap();
+        let pk1 = PublicKey::from_compressed(&pk.to_compressed().unwrap()).unwrap();
+        let pk2 = PublicKey::from_slice(&pk.0).unwrap();
+        assert_eq!(pk1, pk2);
+
+        // hybrid encoding: 0x06 or 0x07 prefix, depending on the parity of y.
+        let mut hybrid = pk;
+        hybrid.0[0] = 0x06 | (pk.0[64] & 1);
+        assert_ne!(hybrid.0, pk.0);
+        assert_eq!(hybrid, pk);
+        assert_ne!(PrivateKey::random().to_public().unwrap(), pk);
     }
 }
@@ -0,0 +1,361 @@
+// Copyright (C) 2022 The Elabs Authors.
+// This file is part of the Elabs.
+//
+// Elabs is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Elabs is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Elabs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Web3 Secret Storage (V3 keystore) encryption and decryption.
+//! Keys are encrypted with scrypt and AES-128-CTR; decryption also accepts
+//! pbkdf2 (hmac-sha256) keystores. The derived key and the decrypted bytes
+//! are zeroized before their buffers are released.
+
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::*;
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+// scrypt cost parameters used when encrypting: n = 2^13, r = 8, p = 1.
+const SCRYPT_LOG_N: u8 = 13;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const DKLEN: usize = 32;
+
+// bounds accepted when decrypting, so a hostile keystore can't exhaust memory or cpu.
+const MIN_DKLEN: usize = 32;
+const MAX_DKLEN: usize = 64;
+const MAX_PBKDF2_ROUNDS: u32 = 1 << 24;
+// scrypt needs about 128 * r * n bytes; geth's standard n is 2^18.
+const MAX_SCRYPT_LOG_N: u32 = 20;
+const MAX_SCRYPT_R: u32 = 16;
+const MAX_SCRYPT_P: u32 = 16;
+
+#[derive(Serialize, Deserialize)]
+struct Keystore {
+    #[serde(alias = "Crypto")]
+    crypto: CryptoJson,
+    id: String,
+    version: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CryptoJson {
+    cipher: String,
+    cipherparams: CipherParams,
+    ciphertext: String,
+    kdf: String,
+    kdfparams: serde_json::Value,
+    mac: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CipherParams {
+    iv: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ScryptParams {
+    dklen: usize,
+    n: u64,
+    r: u32,
+    p: u32,
+    salt: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Pbkdf2Params {
+    dklen: usize,
+    c: u32,
+    prf: String,
+    salt: String,
+}
+
+impl PrivateKey {
+    /// Encrypt the PrivateKey into a V3 keystore JSON string.
+    /// # Arguments
+    /// * `password` - password used to derive the encryption key.
+    /// # Returns
+    /// * `Ok(String)` - the keystore JSON.
+    /// * `Err(KeyError)` - if the PrivateKey is not a valid secp256k1 SecretKey.
+    pub fn to_keystore(&self, password: &str) -> Result<String, KeyError> {
+        let mut plaintext = Zeroizing::new(self.to_bytes()?);
+        let mut rng = rand::thread_rng();
+        let mut salt = [0u8; 32];
+        let mut iv = [0u8; 16];
+        let mut id = [0u8; 16];
+        rng.fill_bytes(&mut salt);
+        rng.fill_bytes(&mut iv);
+        rng.fill_bytes(&mut id);
+
+        let params = scrypt::Params::new(SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P, DKLEN)
+            .map_err(|_| KeyError::InvalidKeystore)?;
+        let mut derived = Zeroizing::new([0u8; DKLEN]);
+        scrypt::scrypt(password.as_bytes(), &salt, &params, &mut derived[..])
+            .map_err(|_| KeyError::InvalidKeystore)?;
+
+        // encrypt in place, `plaintext` holds the ciphertext from here on.
+        Aes128Ctr::new(derived[..16].into(), &iv.into()).apply_keystream(&mut plaintext[..]);
+        let ciphertext = *plaintext;
+        let mac = keystore_mac(&derived[..], &ciphertext);
+
+        let keystore = Keystore {
+            crypto: CryptoJson {
+                cipher: "aes-128-ctr".to_string(),
+                cipherparams: CipherParams {
+                    iv: hex::encode(iv),
+                },
+                ciphertext: hex::encode(ciphertext),
+                kdf: "scrypt".to_string(),
+                kdfparams: serde_json::to_value(ScryptParams {
+                    dklen: DKLEN,
+                    n: 1 << SCRYPT_LOG_N,
+                    r: SCRYPT_R,
+                    p: SCRYPT_P,
+                    salt: hex::encode(salt),
+                })
+                .map_err(|_| KeyError::InvalidKeystore)?,
+                mac: hex::encode(mac),
+            },
+            id: uuid_v4(id),
+            version: 3,
+        };
+        serde_json::to_string(&keystore).map_err(|_| KeyError::InvalidKeystore)
+    }
+
+    /// Decrypt a PrivateKey from a V3 keystore JSON string.
+    /// The MAC is checked before anything is decrypted. The derived key and the
+    /// decrypted plaintext are wiped on every path; see `PrivateKey::from_slice`
+    /// for the validation temporary that is not.
+    /// # Arguments
+    /// * `json` - the keystore JSON.
+    /// * `password` - password used to derive the encryption key.
+    /// # Returns
+    /// * `Ok(PrivateKey)` - the decrypted PrivateKey.
+    /// * `Err(KeyError)` - `InvalidPassword` on MAC mismatch, `InvalidKeystore` if the
+    ///   JSON is malformed or uses an unsupported cipher or kdf.
+    pub fn from_keystore(json: &str, password: &str) -> Result<Self, KeyError> {
+        let keystore: Keystore =
+            serde_json::from_str(json).map_err(|_| KeyError::InvalidKeystore)?;
+        let crypto = keystore.crypto;
+        if keystore.version != 3 || crypto.cipher != "aes-128-ctr" {
+            return Err(KeyError::InvalidKeystore);
+        }
+
+        let mut ciphertext = decode_hex(&crypto.ciphertext)?;
+        let iv: [u8; 16] = decode_hex(&crypto.cipherparams.iv)?
+            .try_into()
+            .map_err(|_| KeyError::InvalidKeystore)?;
+        let mac = decode_hex(&crypto.mac)?;
+        let derived = Zeroizing::new(derive_key(&crypto.kdf, crypto.kdfparams, password)?);
+
+        if !bool::from(keystore_mac(&derived, &ciphertext)[..].ct_eq(&mac[..])) {
+            return Err(KeyError::InvalidPassword);
+        }
+
+        // decrypt in place, `ciphertext` holds the plaintext from here on.
+        Aes128Ctr::new(derived[..16].into(), &iv.into()).apply_keystream(&mut ciphertext);
+        let sk = PrivateKey::from_slice(&ciphertext);
+        ciphertext.zeroize();
+        sk
+    }
+}
+
+fn derive_key(kdf: &str, params: serde_json::Value, password: &str) -> Result<Vec<u8>, KeyError> {
+    match kdf {
+        "scrypt" => {
+            let p: ScryptParams =
+                serde_json::from_value(params).map_err(|_| KeyError::InvalidKeystore)?;
+            if !(MIN_DKLEN..=MAX_DKLEN).contains(&p.dklen)
+                || !p.n.is_power_of_two()
+                || p.n < 2
+                || p.n.trailing_zeros() > MAX_SCRYPT_LOG_N
+                || p.r > MAX_SCRYPT_R
+                || p.p > MAX_SCRYPT_P
+            {
+                return Err(KeyError::InvalidKeystore);
+            }
+            let params = scrypt::Params::new(p.n.trailing_zeros() as u8, p.r, p.p, p.dklen)
+                .map_err(|_| KeyError::InvalidKeystore)?;
+            let mut derived = vec![0u8; p.dklen];
+            scrypt::scrypt(
+                password.as_bytes(),
+                &decode_hex(&p.salt)?,
+                &params,
+                &mut derived,
+            )
+            .map_err(|_| KeyError::InvalidKeystore)?;
+            Ok(derived)
+        }
+        "pbkdf2" => {
+            let p: Pbkdf2Params =
+                serde_json::from_value(params).map_err(|_| KeyError::InvalidKeystore)?;
+            if p.prf != "hmac-sha256"
+                || !(MIN_DKLEN..=MAX_DKLEN).contains(&p.dklen)
+                || p.c == 0
+                || p.c > MAX_PBKDF2_ROUNDS
+            {
+                return Err(KeyError::InvalidKeystore);
+            }
+            let mut derived = vec![0u8; p.dklen];
+            pbkdf2::pbkdf2_hmac::<sha2::Sha256>(
+                password.as_bytes(),
+                &decode_hex(&p.salt)?,
+                p.c,
+                &mut derived,
+            );
+            Ok(derived)
+        }
+        _ => Err(KeyError::InvalidKeystore),
+    }
+}
+
+fn keystore_mac(derived: &[u8], ciphertext: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(&derived[16..32]);
+    hasher.update(ciphertext);
+    hasher.finalize()
+}
+
+fn decode_hex(s: &str) -> Result<Vec<u8>, KeyError> {
+    from_hex_any(s).map_err(|_| KeyError::InvalidKeystore)
+}
+
+fn uuid_v4(mut b: [u8; 16]) -> String {
+    b[6] = (b[6] & 0x0f) | 0x40;
+    b[8] = (b[8] & 0x3f) | 0x80;
+    let h = hex::encode(b);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &h[..8],
+        &h[8..12],
+        &h[12..16],
+        &h[16..20],
+        &h[20..]
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_keystore_roundtrip() {
+        let sk = PrivateKey::random();
+        let json = sk.to_keystore("testpassword").unwrap();
+        let sk1 = PrivateKey::from_keystore(&json, "testpassword").unwrap();
+        assert_eq!(sk, sk1);
+
+        let v: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(v["version"], 3);
+        assert_eq!(v["crypto"]["kdf"], "scrypt");
+        assert_eq!(v["id"].as_str().unwrap().len(), 36);
+    }
+
+    #[test]
+    fn test_keystore_wrong_password() {
+        let sk = PrivateKey::random();
+        let json = sk.to_keystore("testpassword").unwrap();
+        assert_eq!(
+            PrivateKey::from_keystore(&json, "wrongpassword"),
+            Err(KeyError::InvalidPassword)
+        );
+    }
+
+    #[test]
+    fn test_keystore_invalid() {
+        assert_eq!(
+            PrivateKey::from_keystore("{}", "testpassword"),
+            Err(KeyError::InvalidKeystore)
+        );
+    }
+
+    // Test vector from the Web3 Secret Storage definition.
+    const PBKDF2_VECTOR: &str = r#"{
+            "crypto": {
+                "cipher": "aes-128-ctr",
+                "cipherparams": {"iv": "6087dab2f9fdbbfaddc31a909735c1e6"},
+                "ciphertext": "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+                "kdf": "pbkdf2",
+                "kdfparams": {
+                    "c": 262144,
+                    "dklen": 32,
+                    "prf": "hmac-sha256",
+                    "salt": "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"
+                },
+                "mac": "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"
+            },
+            "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+            "version": 3
+        }"#;
+
+    #[test]
+    fn test_keystore_pbkdf2_vector() {
+        let sk = PrivateKey::from_keystore(PBKDF2_VECTOR, "testpassword").unwrap();
+        assert_eq!(
+            sk.to_hex().unwrap(),
+            "0x7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d"
+        );
+    }
+
+    #[test]
+    fn test_keystore_kdf_bounds() {
+        let with = |key: &str, value: serde_json::Value| {
+            let mut v: serde_json::Value = serde_json::from_str(PBKDF2_VECTOR).unwrap();
+            v["crypto"]["kdfparams"][key] = value;
+            PrivateKey::from_keystore(&v.to_string(), "testpassword")
+        };
+        for dklen in [0u64, 16, 65, 1_000_000_000_000_000] {
+            assert_eq!(with("dklen", dklen.into()), Err(KeyError::InvalidKeystore));
+        }
+        for c in [0u32, u32::MAX] {
+            assert_eq!(with("c", c.into()), Err(KeyError::InvalidKeystore));
+        }
+
+        let json = PrivateKey::random().to_keystore("testpassword").unwrap();
+        let with_scrypt = |key: &str, value: serde_json::Value| {
+            let mut v: serde_json::Value = serde_json::from_str(&json).unwrap();
+            v["crypto"]["kdfparams"][key] = value;
+            PrivateKey::from_keystore(&v.to_string(), "testpassword")
+        };
+        let cases = [
+            ("dklen", 1_000_000_000_000_000u64),
+            ("n", 1 << (MAX_SCRYPT_LOG_N + 1)),
+            ("n", 1 << 40),
+            ("r", MAX_SCRYPT_R as u64 + 1),
+            ("r", u32::MAX as u64),
+            ("p", MAX_SCRYPT_P as u64 + 1),
+            ("p", u32::MAX as u64),
+        ];
+        for (key, value) in cases {
+            assert_eq!(
+                with_scrypt(key, value.into()),
+                Err(KeyError::InvalidKeystore)
+            );
+        }
+    }
+
+    #[test]
+    fn test_keystore_malformed_mac() {
+        let with_mac = |mac: &str| {
+            let mut v: serde_json::Value = serde_json::from_str(PBKDF2_VECTOR).unwrap();
+            v["crypto"]["mac"] = mac.into();
+            PrivateKey::from_keystore(&v.to_string(), "testpassword")
+        };
+        assert_eq!(with_mac("zz"), Err(KeyError::InvalidKeystore));
+        assert_eq!(with_mac("517ead92"), Err(KeyError::InvalidPassword));
+    }
+}
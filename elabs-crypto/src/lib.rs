@@ -30,23 +30,64 @@
 //!
 //! fn main() {
 //!        let msg = b"hello world";
-//!        let hash = keccak256(msg);
 //!        let sk = PrivateKey::random();
 //!        let pk = sk.to_public().unwrap();
 //!        let sig = sign(msg, sk).unwrap();
 //!        let (recid, bsig) = sig.serialize_compact();
-//!        let pk2 = ecrecover(&hash, &bsig, recid.to_i32() as u8).unwrap();
+//!        let pk2 = ecrecover(msg, &bsig, recid.to_i32() as u8).unwrap();
 //!        assert_eq!(pk, pk2);
 //! }
 //! ```
 
+pub mod abi;
+
+pub mod address;
+pub use address::*;
+
+pub mod eip712;
+pub use eip712::*;
+
+pub mod hd;
+pub use hd::*;
+
+pub mod hex_util;
+pub use hex_util::*;
+
 pub mod keys;
 pub use keys::*;
 
+pub mod keystore;
+
+pub mod merkle;
+pub use merkle::*;
+
+pub mod message;
+pub use message::*;
+
+pub mod rlp;
+
 pub mod signer;
 pub use signer::*;
 
-use tiny_keccak::{Hasher, Keccak};
+pub mod transaction;
+pub use transaction::*;
+
+use std::io::Read;
+
+use tiny_keccak::{Hasher, Keccak, Sha3};
+
+/// calculate and return keccak224 hash of the input data.
+/// # Arguments
+/// * `data` - input data
+/// # Returns
+/// keccak224 hash of the input data.
+pub fn keccak224(data: &[u8]) -> [u8; 28] {
+    let mut buf = [0u8; 28];
+    let mut hasher = Keccak::v224();
+    hasher.update(data);
+    hasher.finalize(&mut buf);
+    buf
+}
 
 /// calculate and return keccak256 hash of the input data.
 /// # Arguments
@@ -61,6 +102,74 @@ pub fn keccak256(data: &[u8]) -> [u8; 32] {
     buf
 }
 
+/// Incremental keccak256 hasher.
+/// Feed the input in chunks with `update`, then call `finalize`.
+#[derive(Clone)]
+pub struct Keccak256(Keccak);
+
+impl Keccak256 {
+    /// Create a new keccak256 hasher.
+    pub fn new() -> Self {
+        Keccak256(Keccak::v256())
+    }
+
+    /// Absorb the input data into the hasher.
+    /// # Arguments
+    /// * `data` - input data
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Return the keccak256 hash of all the absorbed data.
+    /// # Returns
+    /// keccak256 hash of the input data.
+    pub fn finalize(self) -> [u8; 32] {
+        let mut buf = [0u8; 32];
+        self.0.finalize(&mut buf);
+        buf
+    }
+}
+
+impl Default for Keccak256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// calculate and return keccak256 hash of a file.
+/// The file is read in 8 KiB chunks, so memory use does not grow with its size.
+/// # Arguments
+/// * `path` - path of the file
+/// # Returns
+/// * `Ok([u8; 32])` - keccak256 hash of the file contents.
+/// * `Err(std::io::Error)` - if the file can't be opened or read.
+pub fn keccak256_file(path: &str) -> std::io::Result<[u8; 32]> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Keccak256::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize())
+}
+
+/// calculate and return keccak384 hash of the input data.
+/// # Arguments
+/// * `data` - input data
+/// # Returns
+/// keccak384 hash of the input data.
+pub fn keccak384(data: &[u8]) -> [u8; 48] {
+    let mut buf = [0u8; 48];
+    let mut hasher = Keccak::v384();
+    hasher.update(data);
+    hasher.finalize(&mut buf);
+    buf
+}
+
 /// calculate and return keccak512 hash of the input data.
 /// # Arguments
 /// * `data` - input data
@@ -73,3 +182,219 @@ pub fn keccak512(data: &[u8]) -> [u8; 64] {
     hasher.finalize(&mut buf);
     buf
 }
+
+/// calculate and return keccak256 hash of each concatenated pair.
+/// The hasher is initialised once per call and cloned for every pair, and
+/// pairs are copied into a single stack buffer instead of being allocated.
+/// # Arguments
+/// * `pairs` - input pairs.
+/// # Returns
+/// keccak256(a ++ b) for each pair `(a, b)`, in order.
+pub fn keccak256_pairs(pairs: &[([u8; 32], [u8; 32])]) -> Vec<[u8; 32]> {
+    let template = Keccak::v256();
+    let mut input = [0u8; 64];
+    let mut out = Vec::with_capacity(pairs.len());
+    for (a, b) in pairs {
+        input[..32].copy_from_slice(a);
+        input[32..].copy_from_slice(b);
+        let mut buf = [0u8; 32];
+        let mut hasher = template.clone();
+        hasher.update(&input);
+        hasher.finalize(&mut buf);
+        out.push(buf);
+    }
+    out
+}
+
+/// calculate and return NIST SHA3-256 hash of the input data.
+/// This is **not** the keccak256 used by Ethereum: SHA3 uses a different
+/// padding, so the two produce different digests for the same input.
+/// # Arguments
+/// * `data` - input data
+/// # Returns
+/// SHA3-256 hash of the input data.
+pub fn sha3_256(data: &[u8]) -> [u8; 32] {
+    let mut buf = [0u8; 32];
+    let mut hasher = Sha3::v256();
+    hasher.update(data);
+    hasher.finalize(&mut buf);
+    buf
+}
+
+/// calculate a deterministic salt from the given parts.
+/// # Arguments
+/// * `parts` - input parts, concatenated in order.
+/// # Returns
+/// keccak256 hash of the concatenated parts.
+pub fn salt_from(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut buf = [0u8; 32];
+    hasher.finalize(&mut buf);
+    buf
+}
+
+/// calculate a deterministic CREATE2 salt for a contract deployment.
+/// # Arguments
+/// * `contract_name` - name of the contract.
+/// * `version` - version of the deployment.
+/// # Returns
+/// keccak256 hash of `contract_name ++ "@" ++ version`.
+pub fn deployment_salt(contract_name: &str, version: &str) -> [u8; 32] {
+    salt_from(&[contract_name.as_bytes(), b"@", version.as_bytes()])
+}
+
+/// calculate the ENS namehash of a name.
+/// Labels are processed right-to-left, starting from 32 zero bytes:
+/// `node = keccak256(node ++ keccak256(label))`.
+/// # Arguments
+/// * `name` - dot separated name, e.g. `foo.eth`.
+/// # Returns
+/// namehash of the name, all zeros for the empty name.
+pub fn namehash(name: &str) -> [u8; 32] {
+    let mut node = [0u8; 32];
+    if name.is_empty() {
+        return node;
+    }
+    for label in name.rsplit('.') {
+        node = salt_from(&[&node, &keccak256(label.as_bytes())]);
+    }
+    node
+}
+
+/// calculate the 4 bytes function selector of a function signature.
+/// # Arguments
+/// * `signature` - canonical signature, e.g. `transfer(address,uint256)`.
+/// # Returns
+/// first 4 bytes of the keccak256 hash of the signature.
+pub fn function_selector(signature: &str) -> [u8; 4] {
+    let mut buf = [0u8; 4];
+    buf.copy_from_slice(&keccak256(signature.as_bytes())[..4]);
+    buf
+}
+
+/// calculate the topic of an event signature.
+/// # Arguments
+/// * `signature` - canonical signature, e.g. `Transfer(address,address,uint256)`.
+/// # Returns
+/// keccak256 hash of the signature.
+pub fn event_topic(signature: &str) -> [u8; 32] {
+    keccak256(signature.as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_keccak224() {
+        assert_eq!(
+            hex::encode(keccak224(b"")),
+            "f71837502ba8e10837bdd8d365adb85591895602fc552b48b7390abd"
+        );
+    }
+
+    #[test]
+    fn test_keccak256_file() {
+        let path = std::env::temp_dir().join("elabs_crypto_test_keccak256_file");
+        let data = vec![0xabu8; 20000];
+        std::fs::write(&path, &data).unwrap();
+        let hash = keccak256_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(hash, keccak256(&data));
+
+        let err = keccak256_file("/nonexistent/elabs/file").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_keccak384() {
+        assert_eq!(
+            hex::encode(keccak384(b"")),
+            "2c23146a63a29acf99e73b88f8c24eaa7dc60aa771780ccc006afbfa8fe2479b\
+             2dd2b21362337441ac12b515911957ff"
+        );
+    }
+
+    #[test]
+    fn test_sha3_256() {
+        assert_eq!(
+            hex::encode(sha3_256(b"")),
+            "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+        );
+        assert_ne!(sha3_256(b""), keccak256(b""));
+    }
+
+    #[test]
+    fn test_keccak256_streaming() {
+        let mut hasher = Keccak256::new();
+        hasher.update(b"Hello");
+        hasher.update(b" World");
+        assert_eq!(hasher.finalize(), keccak256(b"Hello World"));
+        assert_eq!(Keccak256::default().finalize(), keccak256(b""));
+    }
+
+    #[test]
+    fn test_keccak256_pairs() {
+        let pairs = [([1u8; 32], [2u8; 32]), ([3u8; 32], [4u8; 32])];
+        let hashes = keccak256_pairs(&pairs);
+        assert_eq!(hashes.len(), 2);
+        for ((a, b), h) in pairs.iter().zip(hashes) {
+            assert_eq!(h, keccak256(&[&a[..], &b[..]].concat()));
+        }
+        assert!(keccak256_pairs(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_salt_from() {
+        assert_eq!(
+            salt_from(&[b"hello", b" ", b"world"]),
+            keccak256(b"hello world")
+        );
+        assert_eq!(salt_from(&[]), keccak256(b""));
+    }
+
+    #[test]
+    fn test_deployment_salt() {
+        assert_eq!(deployment_salt("Token", "1.0.0"), keccak256(b"Token@1.0.0"));
+        assert_ne!(
+            deployment_salt("Token", "1.0.0"),
+            deployment_salt("Token", "1.0.1")
+        );
+    }
+
+    #[test]
+    fn test_namehash() {
+        assert_eq!(namehash(""), [0u8; 32]);
+        assert_eq!(
+            hex::encode(namehash("eth")),
+            "93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae"
+        );
+        assert_eq!(
+            hex::encode(namehash("foo.eth")),
+            "de9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f"
+        );
+    }
+
+    #[test]
+    fn test_function_selector() {
+        assert_eq!(
+            function_selector("transfer(address,uint256)"),
+            [0xa9, 0x05, 0x9c, 0xbb]
+        );
+        assert_eq!(
+            function_selector("balanceOf(address)"),
+            [0x70, 0xa0, 0x82, 0x31]
+        );
+    }
+
+    #[test]
+    fn test_event_topic() {
+        assert_eq!(
+            hex::encode(event_topic("Transfer(address,address,uint256)")),
+            "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+        );
+    }
+}
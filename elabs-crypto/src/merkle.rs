@@ -0,0 +1,339 @@
+// Copyright (C) 2022 The Elabs Authors.
+// This file is part of the Elabs.
+//
+// Elabs is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Elabs is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Elabs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Merkle tree helpers.
+//! Every function takes a `combine` closure which hashes two child nodes
+//! into their parent, so the tree can match the scheme a contract expects.
+//! When a layer has an odd number of nodes, the last node is promoted to
+//! the next layer unchanged. Proofs record the side of each sibling, so they
+//! verify with any `combine`, whether or not it is commutative.
+
+use crate::{keccak256, keccak256_pairs};
+
+/// A step of a merkle proof.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProofNode {
+    /// The sibling node.
+    pub hash: [u8; 32],
+    /// Whether the sibling is the left child, i.e. the parent is `combine(hash, node)`.
+    pub left: bool,
+}
+
+/// Combine two nodes with keccak256 of the sorted pair.
+/// This is the scheme used by OpenZeppelin's `MerkleProof`.
+/// # Arguments
+/// * `a` - first node.
+/// * `b` - second node.
+/// # Returns
+/// keccak256(min(a, b) ++ max(a, b)).
+pub fn keccak_sorted_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let mut buf = [0u8; 64];
+    if a <= b {
+        buf[..32].copy_from_slice(a);
+        buf[32..].copy_from_slice(b);
+    } else {
+        buf[..32].copy_from_slice(b);
+        buf[32..].copy_from_slice(a);
+    }
+    keccak256(&buf)
+}
+
+/// Calculate the merkle root of the given leaves.
+/// # Arguments
+/// * `leaves` - leaves of the tree.
+/// * `combine` - node combine function.
+/// # Returns
+/// The merkle root, or 32 zero bytes if there are no leaves.
+pub fn merkle_root<F>(leaves: &[[u8; 32]], combine: F) -> [u8; 32]
+where
+    F: Fn(&[u8; 32], &[u8; 32]) -> [u8; 32],
+{
+    if leaves.is_empty() {
+        return [0u8; 32];
+    }
+
+    let mut layer = leaves.to_vec();
+    while layer.len() > 1 {
+        layer = next_layer(&layer, &combine);
+    }
+    layer[0]
+}
+
+/// Calculate the merkle proof of the leaf at the given index.
+/// # Arguments
+/// * `leaves` - leaves of the tree.
+/// * `index` - index of the leaf.
+/// * `combine` - node combine function.
+/// # Returns
+/// * `Some(Vec<ProofNode>)` - sibling nodes from the leaf up to the root.
+/// * `None` - if the index is out of range.
+pub fn merkle_proof<F>(leaves: &[[u8; 32]], index: usize, combine: F) -> Option<Vec<ProofNode>>
+where
+    F: Fn(&[u8; 32], &[u8; 32]) -> [u8; 32],
+{
+    if index >= leaves.len() {
+        return None;
+    }
+
+    let mut proof = Vec::new();
+    let mut layer = leaves.to_vec();
+    let mut index = index;
+    while layer.len() > 1 {
+        let sibling = index ^ 1;
+        if sibling < layer.len() {
+            proof.push(ProofNode {
+                hash: layer[sibling],
+                left: sibling < index,
+            });
+        }
+        layer = next_layer(&layer, &combine);
+        index /= 2;
+    }
+    Some(proof)
+}
+
+/// Verify a merkle proof.
+/// # Arguments
+/// * `leaf` - the leaf.
+/// * `proof` - sibling nodes from the leaf up to the root.
+/// * `root` - expected merkle root.
+/// * `combine` - node combine function.
+/// # Returns
+/// Whether the proof is valid.
+pub fn verify_proof<F>(leaf: &[u8; 32], proof: &[ProofNode], root: &[u8; 32], combine: F) -> bool
+where
+    F: Fn(&[u8; 32], &[u8; 32]) -> [u8; 32],
+{
+    let computed = proof.iter().fold(*leaf, |node, sibling| {
+        if sibling.left {
+            combine(&sibling.hash, &node)
+        } else {
+            combine(&node, &sibling.hash)
+        }
+    });
+    &computed == root
+}
+
+/// keccak256 Merkle tree with sorted-pair hashing, compatible with OpenZeppelin's `MerkleProof`.
+/// All layers are kept, so proofs are read without rehashing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleTree {
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    /// Build the tree of the given leaves.
+    /// # Arguments
+    /// * `leaves` - leaves of the tree.
+    pub fn new(leaves: Vec<[u8; 32]>) -> Self {
+        let mut layers = vec![leaves];
+        while layers[layers.len() - 1].len() > 1 {
+            let layer = sorted_pair_layer(&layers[layers.len() - 1]);
+            layers.push(layer);
+        }
+        MerkleTree { layers }
+    }
+
+    /// Return the merkle root, or 32 zero bytes if there are no leaves.
+    pub fn root(&self) -> [u8; 32] {
+        self.layers[self.layers.len() - 1]
+            .first()
+            .copied()
+            .unwrap_or([0u8; 32])
+    }
+
+    /// Return the merkle proof of the leaf at the given index.
+    /// # Arguments
+    /// * `index` - index of the leaf.
+    /// # Returns
+    /// * `Some(Vec<[u8; 32]>)` - sibling nodes from the leaf up to the root.
+    /// * `None` - if the index is out of range.
+    pub fn proof(&self, index: usize) -> Option<Vec<[u8; 32]>> {
+        if index >= self.layers[0].len() {
+            return None;
+        }
+
+        let mut index = index;
+        let mut proof = Vec::new();
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+
+    /// Verify a merkle proof against a root built with sorted-pair hashing.
+    /// Sorted-pair hashing is commutative, so the proof needs no sibling sides.
+    /// # Arguments
+    /// * `leaf` - the leaf.
+    /// * `proof` - sibling nodes from the leaf up to the root.
+    /// * `root` - expected merkle root.
+    /// # Returns
+    /// Whether the proof is valid.
+    pub fn verify_proof(leaf: &[u8; 32], proof: &[[u8; 32]], root: &[u8; 32]) -> bool {
+        let computed = proof
+            .iter()
+            .fold(*leaf, |node, sibling| keccak_sorted_pair(&node, sibling));
+        &computed == root
+    }
+}
+
+fn next_layer<F>(layer: &[[u8; 32]], combine: &F) -> Vec<[u8; 32]>
+where
+    F: Fn(&[u8; 32], &[u8; 32]) -> [u8; 32],
+{
+    layer
+        .chunks(2)
+        .map(|pair| match pair {
+            [a, b] => combine(a, b),
+            [a] => *a,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Same as `next_layer` with `keccak_sorted_pair`, hashing the whole layer
+/// in one `keccak256_pairs` call.
+fn sorted_pair_layer(layer: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    let pairs: Vec<_> = layer
+        .chunks_exact(2)
+        .map(|pair| {
+            if pair[0] <= pair[1] {
+                (pair[0], pair[1])
+            } else {
+                (pair[1], pair[0])
+            }
+        })
+        .collect();
+    let mut next = keccak256_pairs(&pairs);
+    if let [last] = layer.chunks_exact(2).remainder() {
+        next.push(*last);
+    }
+    next
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn leaves() -> Vec<[u8; 32]> {
+        vec![keccak256(b"a"), keccak256(b"b"), keccak256(b"c")]
+    }
+
+    #[test]
+    fn test_merkle_root() {
+        let l = leaves();
+        let ab = keccak_sorted_pair(&l[0], &l[1]);
+        let root = keccak_sorted_pair(&ab, &l[2]);
+        assert_eq!(merkle_root(&l, keccak_sorted_pair), root);
+        assert_eq!(merkle_root(&l[..1], keccak_sorted_pair), l[0]);
+        assert_eq!(merkle_root(&[], keccak_sorted_pair), [0u8; 32]);
+    }
+
+    #[test]
+    fn test_merkle_proof() {
+        let l = leaves();
+        let root = merkle_root(&l, keccak_sorted_pair);
+        for (i, leaf) in l.iter().enumerate() {
+            let proof = merkle_proof(&l, i, keccak_sorted_pair).unwrap();
+            assert!(verify_proof(leaf, &proof, &root, keccak_sorted_pair));
+        }
+        assert!(merkle_proof(&l, 3, keccak_sorted_pair).is_none());
+    }
+
+    #[test]
+    fn test_verify_proof_invalid() {
+        let l = leaves();
+        let root = merkle_root(&l, keccak_sorted_pair);
+        let proof = merkle_proof(&l, 0, keccak_sorted_pair).unwrap();
+        assert!(!verify_proof(&l[1], &proof, &root, keccak_sorted_pair));
+    }
+
+    #[test]
+    fn test_custom_combine() {
+        let l = leaves();
+        let concat = |a: &[u8; 32], b: &[u8; 32]| keccak256(&[&a[..], &b[..]].concat());
+        let root = merkle_root(&l, concat);
+        let ab = concat(&l[0], &l[1]);
+        assert_eq!(root, concat(&ab, &l[2]));
+        assert_ne!(root, merkle_root(&l, keccak_sorted_pair));
+
+        for (i, leaf) in l.iter().enumerate() {
+            let proof = merkle_proof(&l, i, concat).unwrap();
+            assert!(verify_proof(leaf, &proof, &root, concat));
+        }
+        let proof = merkle_proof(&l, 1, concat).unwrap();
+        assert_eq!(
+            proof,
+            [
+                ProofNode {
+                    hash: l[0],
+                    left: true
+                },
+                ProofNode {
+                    hash: l[2],
+                    left: false
+                }
+            ]
+        );
+        assert!(!verify_proof(&l[0], &proof, &root, concat));
+    }
+
+    #[test]
+    fn test_merkle_tree() {
+        let l: Vec<[u8; 32]> = (0u8..4).map(|i| keccak256(&[i])).collect();
+        let sorted = |a: [u8; 32], b: [u8; 32]| {
+            let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+            keccak256(&[lo, hi].concat())
+        };
+        let root = sorted(sorted(l[0], l[1]), sorted(l[2], l[3]));
+
+        let tree = MerkleTree::new(l.clone());
+        assert_eq!(tree.root(), root);
+        assert_eq!(tree.proof(2).unwrap(), [l[3], sorted(l[0], l[1])]);
+        for (i, leaf) in l.iter().enumerate() {
+            let proof = tree.proof(i).unwrap();
+            let nodes = merkle_proof(&l, i, keccak_sorted_pair).unwrap();
+            assert_eq!(proof, nodes.iter().map(|n| n.hash).collect::<Vec<_>>());
+            assert!(MerkleTree::verify_proof(leaf, &proof, &root));
+        }
+        assert!(!MerkleTree::verify_proof(
+            &l[0],
+            &tree.proof(1).unwrap(),
+            &root
+        ));
+        assert!(tree.proof(4).is_none());
+    }
+
+    #[test]
+    fn test_merkle_tree_small() {
+        let l = leaves();
+        assert_eq!(
+            MerkleTree::new(l.clone()).root(),
+            merkle_root(&l, keccak_sorted_pair)
+        );
+        let l7: Vec<_> = (0..7u8).map(|i| keccak256(&[i])).collect();
+        assert_eq!(
+            MerkleTree::new(l7.clone()).root(),
+            merkle_root(&l7, keccak_sorted_pair)
+        );
+        assert_eq!(MerkleTree::new(l[..1].to_vec()).root(), l[0]);
+        assert_eq!(MerkleTree::new(vec![]).root(), [0u8; 32]);
+        assert!(MerkleTree::new(vec![]).proof(0).is_none());
+    }
+}
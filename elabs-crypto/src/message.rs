@@ -0,0 +1,230 @@
+// Copyright (C) 2022 The Elabs Authors.
+// This file is part of the Elabs.
+//
+// Elabs is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Elabs is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Elabs.  If not, see <https://www.gnu.org/licenses/>.
+
+use secp256k1::Error;
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::*;
+
+/// Hashing scheme applied to a message before signing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SigScheme {
+    /// keccak256 of the raw message.
+    Raw,
+    /// EIP-191 personal message (`"\x19Ethereum Signed Message:\n" + len(msg)` prefix).
+    Personal,
+    /// EIP-712 typed data. The message is `domainSeparator ++ hashStruct(message)`.
+    Eip712,
+}
+
+impl SigScheme {
+    /// Return the digest that is signed for the given message.
+    /// # Arguments
+    /// * `message` - The message.
+    /// # Returns
+    /// * `Some([u8; 32])` - The digest.
+    /// * `None` - if the scheme is `Eip712` and the message is not 64 bytes.
+    pub fn digest(&self, message: &[u8]) -> Option<[u8; 32]> {
+        match self {
+            SigScheme::Raw => Some(keccak256(message)),
+            SigScheme::Personal => Some(hash_message(message)),
+            SigScheme::Eip712 => {
+                if message.len() != 64 {
+                    return None;
+                }
+                let (domain_separator, struct_hash) = message.split_at(32);
+                Some(hash_typed_data(
+                    domain_separator.try_into().unwrap(),
+                    struct_hash.try_into().unwrap(),
+                ))
+            }
+        }
+    }
+}
+
+/// Current version of the [`SignedMessage`] envelope format.
+pub const SIGNED_MESSAGE_VERSION: u8 = 1;
+
+/// A message together with its signature, signer and hashing scheme.
+/// The signature is 65 bytes `r ++ s ++ v`, with `v` being 27 or 28.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedMessage {
+    /// Version of the envelope format, missing versions are read as 1.
+    #[serde(default = "default_version", deserialize_with = "deserialize_version")]
+    pub version: u8,
+    /// The message.
+    #[serde(with = "hex_bytes")]
+    pub message: Vec<u8>,
+    /// The signature.
+    #[serde(with = "hex_signature")]
+    pub signature: [u8; 65],
+    /// Address of the signer.
+    pub signer: Address,
+    /// Hashing scheme used to sign the message.
+    pub scheme: SigScheme,
+}
+
+impl SignedMessage {
+    /// Sign a message with the given private key.
+    /// # Arguments
+    /// * `message` - The message.
+    /// * `scheme` - The hashing scheme.
+    /// * `private_key` - The private key.
+    /// # Returns
+    /// * `Ok(SignedMessage)` - The signed message.
+    /// * `Err(Error)` - The error that occurred.
+    pub fn sign(message: &[u8], scheme: SigScheme, private_key: PrivateKey) -> Result<Self, Error> {
+        let hash = scheme.digest(message).ok_or(Error::InvalidMessage)?;
+        let pk = private_key
+            .to_public()
+            .map_err(|_| Error::InvalidSecretKey)?;
+        let (recid, bsig) = sign_hash(&hash, private_key)?.serialize_compact();
+
+        let mut signature = [0u8; 65];
+        signature[..64].copy_from_slice(&bsig);
+        signature[64] = apply_v(recid.to_i32() as u8, None)? as u8;
+
+        Ok(SignedMessage {
+            version: SIGNED_MESSAGE_VERSION,
+            message: message.to_vec(),
+            signature,
+            signer: Address::from_public_key(&pk),
+            scheme,
+        })
+    }
+
+    /// Verify that the signature was made by `signer` over `message` using `scheme`.
+    /// # Returns
+    /// Whether the signature is valid.
+    pub fn verify(&self) -> bool {
+        self.recover() == Some(self.signer)
+    }
+
+    fn recover(&self) -> Option<Address> {
+        let hash = self.scheme.digest(&self.message)?;
+        let recid = normalize_v(self.signature[64] as u64, None).ok()?;
+        let pk = ecrecover_hash(&hash, &self.signature[..64], recid).ok()?;
+        Some(Address::from_public_key(&pk))
+    }
+}
+
+fn default_version() -> u8 {
+    SIGNED_MESSAGE_VERSION
+}
+
+fn deserialize_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+    let version = u8::deserialize(deserializer)?;
+    if version != SIGNED_MESSAGE_VERSION {
+        return Err(de::Error::custom(format!(
+            "unsupported signed message version {}",
+            version
+        )));
+    }
+    Ok(version)
+}
+
+mod hex_bytes {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&crate::to_hex_prefixed(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        crate::from_hex_any(&s).map_err(de::Error::custom)
+    }
+}
+
+mod hex_signature {
+    use serde::{de, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8; 65], serializer: S) -> Result<S::Ok, S::Error> {
+        super::hex_bytes::serialize(bytes, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 65], D::Error> {
+        let buf = super::hex_bytes::deserialize(deserializer)?;
+        buf.try_into()
+            .map_err(|_| de::Error::custom("signature must be 65 bytes"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_signed_message_verify() {
+        let sk = PrivateKey::random();
+        for scheme in [SigScheme::Raw, SigScheme::Personal, SigScheme::Eip712] {
+            let msg = [7u8; 64];
+            let signed = SignedMessage::sign(&msg, scheme, sk.clone()).unwrap();
+            assert!(signed.verify());
+        }
+    }
+
+    #[test]
+    fn test_signed_message_wrong_scheme() {
+        let sk = PrivateKey::random();
+        let mut signed = SignedMessage::sign(b"hello world", SigScheme::Raw, sk).unwrap();
+        signed.scheme = SigScheme::Personal;
+        assert!(!signed.verify());
+    }
+
+    #[test]
+    fn test_signed_message_tampered() {
+        let sk = PrivateKey::random();
+        let mut signed = SignedMessage::sign(b"hello world", SigScheme::Personal, sk).unwrap();
+        signed.message = b"hello there".to_vec();
+        assert!(!signed.verify());
+    }
+
+    #[test]
+    fn test_signed_message_eip712_length() {
+        let sk = PrivateKey::random();
+        assert!(SignedMessage::sign(b"short", SigScheme::Eip712, sk).is_err());
+    }
+
+    #[test]
+    fn test_signed_message_serde() {
+        let sk = PrivateKey::random();
+        let signed = SignedMessage::sign(b"hello world", SigScheme::Personal, sk).unwrap();
+        let json = serde_json::to_string(&signed).unwrap();
+        assert!(json.contains("\"scheme\":\"personal\""));
+        let decoded: SignedMessage = serde_json::from_str(&json).unwrap();
+        assert_eq!(signed, decoded);
+        assert!(decoded.verify());
+    }
+
+    #[test]
+    fn test_signed_message_version() {
+        let sk = PrivateKey::random();
+        let signed = SignedMessage::sign(b"hello world", SigScheme::Personal, sk).unwrap();
+        assert_eq!(signed.version, SIGNED_MESSAGE_VERSION);
+
+        let mut json: serde_json::Value = serde_json::to_value(&signed).unwrap();
+        assert_eq!(json["version"], 1);
+
+        json.as_object_mut().unwrap().remove("version");
+        let decoded: SignedMessage = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(decoded, signed);
+
+        json["version"] = 2.into();
+        assert!(serde_json::from_value::<SignedMessage>(json).is_err());
+    }
+}
@@ -0,0 +1,413 @@
+// Copyright (C) 2022 The Elabs Authors.
+// This file is part of the Elabs.
+//
+// Elabs is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Elabs is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Elabs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Recursive Length Prefix (RLP) serialization.
+
+use std::fmt::Display;
+
+use crate::Address;
+
+/// RLP item.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RlpItem {
+    /// Byte string.
+    Bytes(Vec<u8>),
+    /// List of items.
+    List(Vec<RlpItem>),
+}
+
+impl RlpItem {
+    /// Create a byte string item of a big-endian unsigned integer.
+    /// Leading zero bytes are stripped, so zero encodes as the empty string.
+    /// # Arguments
+    /// * `be` - big-endian bytes of the integer.
+    /// # Returns
+    /// Byte string item.
+    pub fn from_uint(be: &[u8]) -> Self {
+        let start = be.iter().position(|b| *b != 0).unwrap_or(be.len());
+        RlpItem::Bytes(be[start..].to_vec())
+    }
+
+    /// Create a byte string item of a u64.
+    /// # Arguments
+    /// * `n` - the integer.
+    /// # Returns
+    /// Byte string item.
+    pub fn from_u64(n: u64) -> Self {
+        RlpItem::from_uint(&n.to_be_bytes())
+    }
+
+    /// Return the bytes of the item.
+    /// # Returns
+    /// * `Ok(&[u8])` - if the item is a byte string.
+    /// * `Err(RlpError)` - if the item is a list.
+    pub fn as_bytes(&self) -> Result<&[u8], RlpError> {
+        match self {
+            RlpItem::Bytes(b) => Ok(b),
+            RlpItem::List(_) => Err(RlpError::ExpectedBytes),
+        }
+    }
+
+    /// Return the items of the list.
+    /// # Returns
+    /// * `Ok(&[RlpItem])` - if the item is a list.
+    /// * `Err(RlpError)` - if the item is a byte string.
+    pub fn as_list(&self) -> Result<&[RlpItem], RlpError> {
+        match self {
+            RlpItem::List(l) => Ok(l),
+            RlpItem::Bytes(_) => Err(RlpError::ExpectedList),
+        }
+    }
+
+    /// Return the item as a big-endian u64.
+    /// # Returns
+    /// * `Ok(u64)` - if the item is a canonical integer of at most 8 bytes.
+    /// * `Err(RlpError)` - if the item is a list, has leading zeros or is too large.
+    pub fn as_u64(&self) -> Result<u64, RlpError> {
+        let b = self.as_integer(8)?;
+        Ok(b.iter().fold(0u64, |acc, x| (acc << 8) | *x as u64))
+    }
+
+    /// Return the item as a big-endian 256-bit integer, left padded to 32 bytes.
+    /// # Returns
+    /// * `Ok([u8; 32])` - if the item is a canonical integer of at most 32 bytes.
+    /// * `Err(RlpError)` - if the item is a list, has leading zeros or is too large.
+    pub fn as_u256(&self) -> Result<[u8; 32], RlpError> {
+        let b = self.as_integer(32)?;
+        let mut buf = [0u8; 32];
+        buf[32 - b.len()..].copy_from_slice(b);
+        Ok(buf)
+    }
+
+    /// Return the item as an Address.
+    /// # Returns
+    /// * `Ok(Address)` - if the item is a 20 byte string.
+    /// * `Err(RlpError)` - if the item is a list or is not 20 bytes.
+    pub fn as_address(&self) -> Result<Address, RlpError> {
+        Address::from_slice(self.as_bytes()?).map_err(|_| RlpError::InvalidLength)
+    }
+
+    fn as_integer(&self, max: usize) -> Result<&[u8], RlpError> {
+        let b = self.as_bytes()?;
+        if b.len() > max {
+            return Err(RlpError::Overflow);
+        }
+        if b.first() == Some(&0) {
+            return Err(RlpError::NonCanonical);
+        }
+        Ok(b)
+    }
+}
+
+/// Encode an RLP item.
+/// # Arguments
+/// * `item` - the item.
+/// # Returns
+/// RLP encoded bytes.
+pub fn encode(item: &RlpItem) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode_into(item, &mut out);
+    out
+}
+
+fn encode_into(item: &RlpItem, out: &mut Vec<u8>) {
+    match item {
+        RlpItem::Bytes(b) => {
+            if b.len() == 1 && b[0] < 0x80 {
+                out.push(b[0]);
+            } else {
+                encode_length(b.len(), 0x80, out);
+                out.extend_from_slice(b);
+            }
+        }
+        RlpItem::List(items) => {
+            let mut payload = Vec::new();
+            for item in items {
+                encode_into(item, &mut payload);
+            }
+            encode_length(payload.len(), 0xc0, out);
+            out.extend_from_slice(&payload);
+        }
+    }
+}
+
+fn encode_length(len: usize, offset: u8, out: &mut Vec<u8>) {
+    if len < 56 {
+        out.push(offset + len as u8);
+    } else {
+        let be = len.to_be_bytes();
+        let start = be.iter().position(|b| *b != 0).unwrap();
+        out.push(offset + 55 + (be.len() - start) as u8);
+        out.extend_from_slice(&be[start..]);
+    }
+}
+
+/// Maximum nesting depth of lists accepted by [`decode`].
+pub const MAX_DEPTH: usize = 128;
+
+/// Decode a single RLP item.
+/// Lists nested deeper than [`MAX_DEPTH`] are rejected.
+/// # Arguments
+/// * `data` - RLP encoded data.
+/// # Returns
+/// * `Ok(RlpItem)` - the decoded item.
+/// * `Err(RlpError)` - if the data is malformed or has trailing bytes.
+pub fn decode(data: &[u8]) -> Result<RlpItem, RlpError> {
+    let (item, consumed) = decode_item(data, 0)?;
+    if consumed != data.len() {
+        return Err(RlpError::TrailingBytes);
+    }
+    Ok(item)
+}
+
+// decode the item at the start of `data`, returning it with the number of bytes consumed.
+fn decode_item(data: &[u8], depth: usize) -> Result<(RlpItem, usize), RlpError> {
+    let prefix = *data.first().ok_or(RlpError::UnexpectedEnd)?;
+    match prefix {
+        0x00..=0x7f => Ok((RlpItem::Bytes(vec![prefix]), 1)),
+        0x80..=0xb7 => {
+            let len = (prefix - 0x80) as usize;
+            let payload = payload(data, 1, len)?;
+            if len == 1 && payload[0] < 0x80 {
+                return Err(RlpError::NonCanonical);
+            }
+            Ok((RlpItem::Bytes(payload.to_vec()), 1 + len))
+        }
+        0xb8..=0xbf => {
+            let len_of_len = (prefix - 0xb7) as usize;
+            let len = long_length(data, len_of_len)?;
+            let payload = payload(data, 1 + len_of_len, len)?;
+            Ok((RlpItem::Bytes(payload.to_vec()), 1 + len_of_len + len))
+        }
+        0xc0..=0xf7 => {
+            let len = (prefix - 0xc0) as usize;
+            let payload = payload(data, 1, len)?;
+            Ok((RlpItem::List(decode_list(payload, depth + 1)?), 1 + len))
+        }
+        0xf8..=0xff => {
+            let len_of_len = (prefix - 0xf7) as usize;
+            let len = long_length(data, len_of_len)?;
+            let payload = payload(data, 1 + len_of_len, len)?;
+            Ok((
+                RlpItem::List(decode_list(payload, depth + 1)?),
+                1 + len_of_len + len,
+            ))
+        }
+    }
+}
+
+fn decode_list(mut data: &[u8], depth: usize) -> Result<Vec<RlpItem>, RlpError> {
+    if depth > MAX_DEPTH {
+        return Err(RlpError::TooDeep);
+    }
+    let mut items = Vec::new();
+    while !data.is_empty() {
+        let (item, consumed) = decode_item(data, depth)?;
+        items.push(item);
+        data = &data[consumed..];
+    }
+    Ok(items)
+}
+
+fn payload(data: &[u8], offset: usize, len: usize) -> Result<&[u8], RlpError> {
+    let end = offset.checked_add(len).ok_or(RlpError::Overflow)?;
+    data.get(offset..end).ok_or(RlpError::UnexpectedEnd)
+}
+
+fn long_length(data: &[u8], len_of_len: usize) -> Result<usize, RlpError> {
+    let b = payload(data, 1, len_of_len)?;
+    if b[0] == 0 {
+        return Err(RlpError::NonCanonical);
+    }
+    if len_of_len > std::mem::size_of::<usize>() {
+        return Err(RlpError::Overflow);
+    }
+    let len = b.iter().fold(0usize, |acc, x| (acc << 8) | *x as usize);
+    if len < 56 {
+        return Err(RlpError::NonCanonical);
+    }
+    Ok(len)
+}
+
+/// RLP error type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RlpError {
+    /// Input ended before the item was complete.
+    UnexpectedEnd,
+    /// Input has bytes after the item.
+    TrailingBytes,
+    /// Item is not in its shortest encoding.
+    NonCanonical,
+    /// Value does not fit in the requested type.
+    Overflow,
+    /// Expected a byte string, got a list.
+    ExpectedBytes,
+    /// Expected a list, got a byte string.
+    ExpectedList,
+    /// Byte string has the wrong length for the requested type.
+    InvalidLength,
+    /// Lists are nested deeper than [`MAX_DEPTH`].
+    TooDeep,
+}
+
+impl Display for RlpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RlpError::UnexpectedEnd => write!(f, "Unexpected end of input"),
+            RlpError::TrailingBytes => write!(f, "Trailing bytes after item"),
+            RlpError::NonCanonical => write!(f, "Non-canonical encoding"),
+            RlpError::Overflow => write!(f, "Value overflow"),
+            RlpError::ExpectedBytes => write!(f, "Expected byte string"),
+            RlpError::ExpectedList => write!(f, "Expected list"),
+            RlpError::InvalidLength => write!(f, "Invalid length"),
+            RlpError::TooDeep => write!(f, "Lists nested too deep"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(&RlpItem::Bytes(vec![])), [0x80]);
+        assert_eq!(encode(&RlpItem::Bytes(vec![0x0f])), [0x0f]);
+        assert_eq!(encode(&RlpItem::Bytes(vec![0x80])), [0x81, 0x80]);
+        assert_eq!(
+            encode(&RlpItem::Bytes(b"dog".to_vec())),
+            [0x83, b'd', b'o', b'g']
+        );
+        assert_eq!(encode(&RlpItem::List(vec![])), [0xc0]);
+        assert_eq!(
+            encode(&RlpItem::List(vec![
+                RlpItem::Bytes(b"cat".to_vec()),
+                RlpItem::Bytes(b"dog".to_vec()),
+            ])),
+            [0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g']
+        );
+
+        let long = RlpItem::Bytes(vec![0xaa; 56]);
+        let encoded = encode(&long);
+        assert_eq!(encoded[..2], [0xb8, 56]);
+        assert_eq!(decode(&encoded).unwrap(), long);
+    }
+
+    #[test]
+    fn test_encode_uint() {
+        assert_eq!(encode(&RlpItem::from_u64(0)), [0x80]);
+        assert_eq!(encode(&RlpItem::from_u64(15)), [0x0f]);
+        assert_eq!(encode(&RlpItem::from_u64(1024)), [0x82, 0x04, 0x00]);
+        assert_eq!(RlpItem::from_uint(&[0, 0, 1]), RlpItem::Bytes(vec![1]));
+    }
+
+    #[test]
+    fn test_decode_bytes() {
+        assert_eq!(decode(&[0x0f]).unwrap(), RlpItem::Bytes(vec![0x0f]));
+        assert_eq!(decode(&[0x80]).unwrap(), RlpItem::Bytes(vec![]));
+        assert_eq!(
+            decode(&[0x83, b'd', b'o', b'g']).unwrap(),
+            RlpItem::Bytes(b"dog".to_vec())
+        );
+
+        let mut long = vec![0xb8, 56];
+        long.extend_from_slice(&[0xaa; 56]);
+        assert_eq!(decode(&long).unwrap(), RlpItem::Bytes(vec![0xaa; 56]));
+    }
+
+    #[test]
+    fn test_decode_list() {
+        assert_eq!(decode(&[0xc0]).unwrap(), RlpItem::List(vec![]));
+        let data = [0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g'];
+        assert_eq!(
+            decode(&data).unwrap(),
+            RlpItem::List(vec![
+                RlpItem::Bytes(b"cat".to_vec()),
+                RlpItem::Bytes(b"dog".to_vec()),
+            ])
+        );
+
+        // [ [], [[]], [ [], [[]] ] ]
+        let data = [0xc7, 0xc0, 0xc1, 0xc0, 0xc3, 0xc0, 0xc1, 0xc0];
+        let item = decode(&data).unwrap();
+        assert_eq!(item.as_list().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(decode(&[]), Err(RlpError::UnexpectedEnd));
+        assert_eq!(decode(&[0x83, b'd', b'o']), Err(RlpError::UnexpectedEnd));
+        assert_eq!(decode(&[0x0f, 0x0f]), Err(RlpError::TrailingBytes));
+        assert_eq!(decode(&[0x81, 0x05]), Err(RlpError::NonCanonical));
+        assert_eq!(decode(&[0xb8, 0x01, 0xaa]), Err(RlpError::NonCanonical));
+    }
+
+    #[test]
+    fn test_item_as_u64() {
+        assert_eq!(decode(&[0x80]).unwrap().as_u64().unwrap(), 0);
+        assert_eq!(decode(&[0x0f]).unwrap().as_u64().unwrap(), 15);
+        assert_eq!(decode(&[0x82, 0x04, 0x00]).unwrap().as_u64().unwrap(), 1024);
+        assert_eq!(
+            decode(&[0x82, 0x00, 0x04]).unwrap().as_u64(),
+            Err(RlpError::NonCanonical)
+        );
+        assert_eq!(
+            decode(&[0x89, 1, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap().as_u64(),
+            Err(RlpError::Overflow)
+        );
+        assert_eq!(
+            decode(&[0xc0]).unwrap().as_u64(),
+            Err(RlpError::ExpectedBytes)
+        );
+    }
+
+    #[test]
+    fn test_item_as_u256() {
+        let v = decode(&[0x82, 0x04, 0x00]).unwrap().as_u256().unwrap();
+        assert_eq!(v[30..], [0x04, 0x00]);
+        assert_eq!(v[..30], [0u8; 30]);
+    }
+
+    #[test]
+    fn test_item_as_address() {
+        let mut data = vec![0x94];
+        data.extend_from_slice(&[0x11; 20]);
+        let addr = decode(&data).unwrap().as_address().unwrap();
+        assert_eq!(addr, Address([0x11; 20]));
+        assert_eq!(
+            decode(&[0x0f]).unwrap().as_address(),
+            Err(RlpError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn test_decode_too_deep() {
+        // `depth` lists, each wrapping the next one, built back to front.
+        let nested = |depth: usize| {
+            let mut rev = vec![0xc0];
+            for _ in 1..depth {
+                let mut header = Vec::new();
+                encode_length(rev.len(), 0xc0, &mut header);
+                rev.extend(header.iter().rev());
+            }
+            rev.reverse();
+            rev
+        };
+        assert!(decode(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(decode(&nested(MAX_DEPTH + 1)), Err(RlpError::TooDeep));
+        assert_eq!(decode(&nested(200_000)), Err(RlpError::TooDeep));
+    }
+}
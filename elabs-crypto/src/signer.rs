@@ -14,30 +14,194 @@
 // You should have received a copy of the GNU General Public License
 // along with Elabs.  If not, see <https://www.gnu.org/licenses/>.
 
+//! ECDSA signing and recovery.
+//!
+//! Every function taking a `msg` hashes it with keccak256 before signing,
+//! verifying or recovering: [`sign`], [`verify`], [`ecrecover`],
+//! [`recover_address`], [`recover_all`].
+//! Their `_hash` counterparts take the 32 bytes digest and use it as is:
+//! [`sign_hash`], [`verify_hash`], [`ecrecover_hash`], as well as [`recover_with`].
+//! So `sign(msg)` pairs with `verify(msg)` and `ecrecover(msg)`, and
+//! `sign_hash(hash)` pairs with `verify_hash(hash)` and `ecrecover_hash(hash)`.
+
 use secp256k1::{
     ecdsa::{self, RecoverableSignature, RecoveryId},
-    Error, Message, Secp256k1,
+    All, Error, Message, Secp256k1, SECP256K1,
 };
 
 use crate::*;
 
-/// Return the public key that created the given signature.
+/// Return the public key that created the given signature of a message.
+/// The message is hashed with keccak256, it is the counterpart of [`sign`].
+/// # Arguments
+/// * `msg` - The message.
+/// * `signature` - The signature.
+/// * `recovery_id` - The recovery id.
+/// # Returns
+/// * `Ok(PublicKey)` - The public key that created the signature.
+/// * `Err(Error)` - The error that occurred.
+pub fn ecrecover(msg: &[u8], signature: &[u8], recovery_id: u8) -> Result<PublicKey, Error> {
+    ecrecover_hash(&keccak256(msg), signature, recovery_id)
+}
+
+/// Return the public key that created the given signature of a 32 bytes hash.
+/// The hash is used as is, it is the counterpart of [`sign_hash`].
+/// # Arguments
+/// * `hash` - The hash.
+/// * `signature` - The signature.
+/// * `recovery_id` - The recovery id.
+/// # Returns
+/// * `Ok(PublicKey)` - The public key that created the signature.
+/// * `Err(Error)` - The error that occurred.
+pub fn ecrecover_hash(
+    hash: &[u8; 32],
+    signature: &[u8],
+    recovery_id: u8,
+) -> Result<PublicKey, Error> {
+    recover_with(SECP256K1, hash, signature, recovery_id)
+}
+
+/// Return the public key that created the given signature, using the given context.
 /// # Arguments
+/// * `ctx` - The secp256k1 context.
 /// * `hash` - The hash of the message.
 /// * `signature` - The signature.
 /// * `recovery_id` - The recovery id.
 /// # Returns
 /// * `Ok(PublicKey)` - The public key that created the signature.
 /// * `Err(Error)` - The error that occurred.
-pub fn ecrecover(hash: &[u8], signature: &[u8], recovery_id: u8) -> Result<PublicKey, Error> {
-    let secp = Secp256k1::new();
+pub fn recover_with(
+    ctx: &Secp256k1<All>,
+    hash: &[u8; 32],
+    signature: &[u8],
+    recovery_id: u8,
+) -> Result<PublicKey, Error> {
     let id = RecoveryId::from_i32(recovery_id as i32)?;
-    let sig = RecoverableSignature::from_compact(&signature, id)?;
-    let msgb = Message::from_slice(&hash)?;
-    let pk = secp.recover_ecdsa(&msgb, &sig)?;
+    let sig = RecoverableSignature::from_compact(signature, id)?;
+    let msgb = Message::from_slice(hash)?;
+    let pk = ctx.recover_ecdsa(&msgb, &sig)?;
     Ok(PublicKey::from_secp256k1(pk))
 }
 
+/// Return the address that created the given signature.
+/// # Arguments
+/// * `msg` - The message.
+/// * `signature` - The signature.
+/// * `recovery_id` - The recovery id.
+/// # Returns
+/// * `Ok(Address)` - The address that created the signature.
+/// * `Err(Error)` - The error that occurred.
+pub fn recover_address(msg: &[u8], signature: &[u8], recovery_id: u8) -> Result<Address, Error> {
+    let pk = ecrecover(msg, signature, recovery_id)?;
+    Ok(Address::from_public_key(&pk))
+}
+
+/// Return the candidate public keys of a signature without recovery id.
+/// Both recovery ids are tried; compare the candidates to the expected signer.
+/// # Arguments
+/// * `msg` - The message.
+/// * `signature` - The 64 bytes `r ++ s` signature.
+/// # Returns
+/// The public keys recovered with recovery id 0 and 1, skipping the ones that fail.
+pub fn recover_all(msg: &[u8], signature: &[u8]) -> Vec<PublicKey> {
+    let hash = keccak256(msg);
+    (0..2)
+        .filter_map(|recovery_id| ecrecover_hash(&hash, signature, recovery_id).ok())
+        .collect()
+}
+
+/// Recover the address that signed the given message.
+/// Compare the result to the expected signer to verify the signature.
+/// # Arguments
+/// * `msg` - The message.
+/// * `signature` - The 65 bytes `r ++ s ++ v` signature, `v` being 0/1 or 27/28.
+/// # Returns
+/// * `Ok(Address)` - The address of the signer.
+/// * `Err(Error)` - if `v` is not 0, 1, 27 or 28, or recovery fails.
+pub fn recover_or_verify(msg: &[u8], signature: &[u8; 65]) -> Result<Address, Error> {
+    let recovery_id = match signature[64] {
+        v @ (0 | 1) => v,
+        v => normalize_v(v as u64, None)?,
+    };
+    recover_address(msg, &signature[..64], recovery_id)
+}
+
+/// Convert a wire-format `v` value into a recovery id.
+/// Without a chain id `v` must be 27 or 28, with a chain id it must be
+/// `35 + chain_id * 2` or `36 + chain_id * 2` (EIP-155).
+/// # Arguments
+/// * `v` - The `v` value of the signature.
+/// * `chain_id` - The chain id, if the signature is EIP-155 replay protected.
+/// # Returns
+/// * `Ok(u8)` - The recovery id, 0 or 1.
+/// * `Err(Error)` - if `v` is not valid for the chain id.
+pub fn normalize_v(v: u64, chain_id: Option<u64>) -> Result<u8, Error> {
+    let base = match chain_id {
+        Some(id) => id
+            .checked_mul(2)
+            .and_then(|x| x.checked_add(35))
+            .ok_or(Error::InvalidRecoveryId)?,
+        None => 27,
+    };
+    match v.checked_sub(base) {
+        Some(id @ (0 | 1)) => Ok(id as u8),
+        _ => Err(Error::InvalidRecoveryId),
+    }
+}
+
+/// Convert a recovery id into a wire-format `v` value.
+/// # Arguments
+/// * `recovery_id` - The recovery id, 0 or 1.
+/// * `chain_id` - The chain id, to produce an EIP-155 `v`.
+/// # Returns
+/// * `Ok(u64)` - `recovery_id + 27`, or `recovery_id + 35 + chain_id * 2` with a chain id.
+/// * `Err(Error)` - if the recovery id is not 0 or 1, or `v` does not fit in a u64.
+pub fn apply_v(recovery_id: u8, chain_id: Option<u64>) -> Result<u64, Error> {
+    if recovery_id > 1 {
+        return Err(Error::InvalidRecoveryId);
+    }
+    let base = match chain_id {
+        Some(id) => id
+            .checked_mul(2)
+            .and_then(|x| x.checked_add(35))
+            .ok_or(Error::InvalidRecoveryId)?,
+        None => 27,
+    };
+    base.checked_add(recovery_id as u64)
+        .ok_or(Error::InvalidRecoveryId)
+}
+
+/// Return the EIP-191 personal message hash of the given message.
+/// The message is prefixed with `"\x19Ethereum Signed Message:\n" + len(msg)` before hashing.
+/// # Arguments
+/// * `msg` - The message.
+/// # Returns
+/// keccak256 hash of the prefixed message.
+pub fn hash_message(msg: &[u8]) -> [u8; 32] {
+    let mut buf = format!("\x19Ethereum Signed Message:\n{}", msg.len()).into_bytes();
+    buf.extend_from_slice(msg);
+    keccak256(&buf)
+}
+
+/// Return the low-S form of a signature.
+/// A high `s` is replaced by `n - s` and the recovery id flipped, so the
+/// signature still recovers the same public key.
+/// # Arguments
+/// * `sig` - The signature.
+/// # Returns
+/// The normalized signature, unchanged if `s` is already low.
+pub fn normalize_signature(sig: &RecoverableSignature) -> RecoverableSignature {
+    let (recid, bsig) = sig.serialize_compact();
+    let mut normalized = ecdsa::Signature::from_compact(&bsig).unwrap();
+    normalized.normalize_s();
+    let nsig = normalized.serialize_compact();
+    if nsig == bsig {
+        return *sig;
+    }
+    let recid = RecoveryId::from_i32(recid.to_i32() ^ 1).unwrap();
+    RecoverableSignature::from_compact(&nsig, recid).unwrap()
+}
+
 /// Sign a message with the given private key.
 /// # Arguments
 /// * `msg` - The message.
@@ -46,10 +210,76 @@ pub fn ecrecover(hash: &[u8], signature: &[u8], recovery_id: u8) -> Result<Publi
 /// * `Ok(Signature)` - The signature.
 /// * `Err(Error)` - The error that occurred.
 pub fn sign(msg: &[u8], private_key: PrivateKey) -> Result<RecoverableSignature, Error> {
-    let secp = Secp256k1::new();
-    let hash = keccak256(msg);
-    let msgb = Message::from_slice(&hash)?;
-    Ok(secp.sign_ecdsa_recoverable(&msgb, &private_key.to_secp256k1().unwrap()))
+    sign_with(SECP256K1, msg, private_key)
+}
+
+/// Sign a message with the given private key, using the given context.
+/// # Arguments
+/// * `ctx` - The secp256k1 context.
+/// * `msg` - The message.
+/// * `private_key` - The private key.
+/// # Returns
+/// * `Ok(Signature)` - The signature.
+/// * `Err(Error)` - The error that occurred.
+pub fn sign_with(
+    ctx: &Secp256k1<All>,
+    msg: &[u8],
+    private_key: PrivateKey,
+) -> Result<RecoverableSignature, Error> {
+    sign_digest(ctx, &keccak256(msg), private_key)
+}
+
+/// Sign a 32 bytes hash with the given private key.
+/// The hash is signed as is, use this for digests that are already hashed,
+/// such as EIP-712 digests or transaction hashes.
+/// # Arguments
+/// * `hash` - The hash.
+/// * `private_key` - The private key.
+/// # Returns
+/// * `Ok(Signature)` - The signature.
+/// * `Err(Error)` - The error that occurred.
+pub fn sign_hash(hash: &[u8; 32], private_key: PrivateKey) -> Result<RecoverableSignature, Error> {
+    sign_digest(SECP256K1, hash, private_key)
+}
+
+fn sign_digest(
+    ctx: &Secp256k1<All>,
+    hash: &[u8; 32],
+    private_key: PrivateKey,
+) -> Result<RecoverableSignature, Error> {
+    let msgb = Message::from_slice(hash)?;
+    let sk = private_key
+        .to_secp256k1()
+        .map_err(|_| Error::InvalidSecretKey)?;
+    Ok(normalize_signature(&ctx.sign_ecdsa_recoverable(&msgb, &sk)))
+}
+
+/// Sign a message with the given private key, using the EIP-191 personal message hash.
+/// Signatures are compatible with `personal_sign` in Ethereum wallets.
+/// # Arguments
+/// * `msg` - The message.
+/// * `private_key` - The private key.
+/// # Returns
+/// * `Ok(Signature)` - The signature.
+/// * `Err(Error)` - The error that occurred.
+pub fn personal_sign(msg: &[u8], private_key: PrivateKey) -> Result<RecoverableSignature, Error> {
+    sign_hash(&hash_message(msg), private_key)
+}
+
+/// Return the public key that created the given EIP-191 personal message signature.
+/// # Arguments
+/// * `msg` - The message.
+/// * `signature` - The signature.
+/// * `recovery_id` - The recovery id.
+/// # Returns
+/// * `Ok(PublicKey)` - The public key that created the signature.
+/// * `Err(Error)` - The error that occurred.
+pub fn personal_ecrecover(
+    msg: &[u8],
+    signature: &[u8],
+    recovery_id: u8,
+) -> Result<PublicKey, Error> {
+    ecrecover_hash(&hash_message(msg), signature, recovery_id)
 }
 
 /// Verify a signature with the given public key.
@@ -61,12 +291,56 @@ pub fn sign(msg: &[u8], private_key: PrivateKey) -> Result<RecoverableSignature,
 /// * `Ok(bool)` - Whether the signature is valid.
 /// * `Err(Error)` - The error that occurred.
 pub fn verify(msg: &[u8], signature: &[u8], public_key: PublicKey) -> Result<bool, Error> {
-    let secp = Secp256k1::new();
-    let hash = keccak256(msg);
-    let msgb = Message::from_slice(&hash)?;
-    let sig = ecdsa::Signature::from_compact(&signature)?;
-    let verify = secp.verify_ecdsa(&msgb, &sig, &public_key.to_secp256k1().unwrap());
-    Ok(verify.is_ok())
+    verify_with(SECP256K1, msg, signature, public_key)
+}
+
+/// Verify a signature with the given public key, using the given context.
+/// # Arguments
+/// * `ctx` - The secp256k1 context.
+/// * `msg` - The message.
+/// * `signature` - The signature.
+/// * `public_key` - The public key.
+/// # Returns
+/// * `Ok(bool)` - Whether the signature is valid.
+/// * `Err(Error)` - The error that occurred.
+pub fn verify_with(
+    ctx: &Secp256k1<All>,
+    msg: &[u8],
+    signature: &[u8],
+    public_key: PublicKey,
+) -> Result<bool, Error> {
+    verify_digest(ctx, &keccak256(msg), signature, public_key)
+}
+
+/// Verify a signature over a 32 bytes hash with the given public key.
+/// The hash is verified as is, it is the counterpart of [`sign_hash`].
+/// # Arguments
+/// * `hash` - The hash.
+/// * `signature` - The signature.
+/// * `public_key` - The public key.
+/// # Returns
+/// * `Ok(bool)` - Whether the signature is valid.
+/// * `Err(Error)` - The error that occurred.
+pub fn verify_hash(
+    hash: &[u8; 32],
+    signature: &[u8],
+    public_key: PublicKey,
+) -> Result<bool, Error> {
+    verify_digest(SECP256K1, hash, signature, public_key)
+}
+
+fn verify_digest(
+    ctx: &Secp256k1<All>,
+    hash: &[u8; 32],
+    signature: &[u8],
+    public_key: PublicKey,
+) -> Result<bool, Error> {
+    let msgb = Message::from_slice(hash)?;
+    let sig = ecdsa::Signature::from_compact(signature)?;
+    let pk = public_key
+        .to_secp256k1()
+        .map_err(|_| Error::InvalidPublicKey)?;
+    Ok(ctx.verify_ecdsa(&msgb, &sig, &pk).is_ok())
 }
 
 #[cfg(test)]
@@ -76,22 +350,241 @@ mod test {
     #[test]
     fn test_ecrecover() {
         let msg = b"hello world";
-        let hash = keccak256(msg);
         let sk = PrivateKey::random();
         let pk = sk.to_public().unwrap();
         let sig = sign(msg, sk).unwrap();
         let (recid, bsig) = sig.serialize_compact();
-        let pk2 = ecrecover(&hash, &bsig, recid.to_i32() as u8).unwrap();
+        let pk2 = ecrecover(msg, &bsig, recid.to_i32() as u8).unwrap();
+        assert_eq!(pk, pk2);
+    }
+
+    #[test]
+    fn test_ecrecover_hash() {
+        let hash = keccak256(b"hello world");
+        let sk = PrivateKey::random();
+        let pk = sk.to_public().unwrap();
+        let (recid, bsig) = sign_hash(&hash, sk).unwrap().serialize_compact();
+        let pk2 = ecrecover_hash(&hash, &bsig, recid.to_i32() as u8).unwrap();
         assert_eq!(pk, pk2);
     }
 
+    #[test]
+    fn test_recover_address() {
+        let msg = b"hello world";
+        let sk = PrivateKey::random();
+        let addr = sk.address().unwrap();
+        let (recid, bsig) = sign(msg, sk).unwrap().serialize_compact();
+        let addr1 = recover_address(msg, &bsig, recid.to_i32() as u8).unwrap();
+        assert_eq!(addr, addr1);
+    }
+
+    #[test]
+    fn test_recover_all() {
+        let msg = b"hello world";
+        let sk = PrivateKey::random();
+        let pk = sk.to_public().unwrap();
+        let (_, bsig) = sign(msg, sk).unwrap().serialize_compact();
+        let candidates = recover_all(msg, &bsig);
+        assert!(!candidates.is_empty() && candidates.len() <= 2);
+        assert!(candidates.contains(&pk));
+        assert!(recover_all(msg, &[0u8; 64]).is_empty());
+    }
+
+    #[test]
+    fn test_recover_or_verify() {
+        let msg = b"hello world";
+        let sk = PrivateKey::random();
+        let addr = Address::from_public_key(&sk.to_public().unwrap());
+        let (recid, bsig) = sign(msg, sk).unwrap().serialize_compact();
+        let mut sig = [0u8; 65];
+        sig[..64].copy_from_slice(&bsig);
+        sig[64] = recid.to_i32() as u8;
+        assert_eq!(recover_or_verify(msg, &sig).unwrap(), addr);
+        sig[64] += 27;
+        assert_eq!(recover_or_verify(msg, &sig).unwrap(), addr);
+        assert_ne!(recover_or_verify(b"hello there", &sig).unwrap(), addr);
+
+        for v in [2, 26, 29, 37] {
+            sig[64] = v;
+            assert_eq!(
+                recover_or_verify(msg, &sig).unwrap_err(),
+                Error::InvalidRecoveryId
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalize_v_legacy() {
+        assert_eq!(normalize_v(27, None).unwrap(), 0);
+        assert_eq!(normalize_v(28, None).unwrap(), 1);
+        assert!(normalize_v(29, None).is_err());
+        assert!(normalize_v(1, None).is_err());
+        assert_eq!(apply_v(0, None).unwrap(), 27);
+        assert_eq!(apply_v(1, None).unwrap(), 28);
+    }
+
+    #[test]
+    fn test_normalize_v_eip155() {
+        // mainnet
+        assert_eq!(normalize_v(37, Some(1)).unwrap(), 0);
+        assert_eq!(normalize_v(38, Some(1)).unwrap(), 1);
+        assert!(normalize_v(27, Some(1)).is_err());
+        assert_eq!(apply_v(0, Some(1)).unwrap(), 37);
+        assert_eq!(apply_v(1, Some(1)).unwrap(), 38);
+
+        // goerli
+        assert_eq!(apply_v(1, Some(5)).unwrap(), 46);
+        assert_eq!(normalize_v(46, Some(5)).unwrap(), 1);
+        assert!(normalize_v(u64::MAX, Some(u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_apply_v_overflow() {
+        let max_id = (u64::MAX - 36) / 2;
+        assert_eq!(apply_v(1, Some(max_id)).unwrap(), u64::MAX - 1);
+        assert_eq!(normalize_v(u64::MAX - 1, Some(max_id)).unwrap(), 1);
+        assert_eq!(
+            apply_v(1, Some(u64::MAX / 2)),
+            Err(Error::InvalidRecoveryId)
+        );
+        assert_eq!(apply_v(0, Some(u64::MAX)), Err(Error::InvalidRecoveryId));
+        assert_eq!(apply_v(2, None), Err(Error::InvalidRecoveryId));
+    }
+
+    #[test]
+    fn test_hash_message() {
+        assert_eq!(
+            hex::encode(hash_message(b"hello world")),
+            "d9eba16ed0ecae432b71fe008c98cc872bb4cc214d3220a36f365326cf807d68"
+        );
+    }
+
+    #[test]
+    fn test_personal_sign() {
+        let sk = PrivateKey::from_hex(
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+        )
+        .unwrap();
+        let (recid, bsig) = personal_sign(b"Some data", sk).unwrap().serialize_compact();
+        assert_eq!(
+            hex::encode(bsig),
+            "b91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd\
+             6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a029"
+        );
+        assert_eq!(recid.to_i32() + 27, 0x1c);
+    }
+
+    #[test]
+    fn test_personal_ecrecover() {
+        let sig = hex::decode(
+            "b91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd\
+             6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a029",
+        )
+        .unwrap();
+        let pk = personal_ecrecover(b"Some data", &sig, 1).unwrap();
+        assert_eq!(
+            Address::from_public_key(&pk).to_hex(),
+            "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23"
+        );
+    }
+
+    #[test]
+    fn test_normalize_signature() {
+        let msg = b"hello world";
+        let sk = PrivateKey::random();
+        let pk = sk.to_public().unwrap();
+        let sig = sign(msg, sk).unwrap();
+        assert_eq!(normalize_signature(&sig), sig);
+
+        // high-S counterpart: s' = n - s, with the recovery id flipped
+        let (recid, bsig) = sig.serialize_compact();
+        let mut high = bsig;
+        let mut borrow = 0i16;
+        for i in (0..32).rev() {
+            let d = secp256k1::constants::CURVE_ORDER[i] as i16 - bsig[32 + i] as i16 - borrow;
+            borrow = (d < 0) as i16;
+            high[32 + i] = d.rem_euclid(256) as u8;
+        }
+        let high_recid = RecoveryId::from_i32(recid.to_i32() ^ 1).unwrap();
+        let high = RecoverableSignature::from_compact(&high, high_recid).unwrap();
+        assert_ne!(high, sig);
+        let (high_recid, high_bsig) = high.serialize_compact();
+        assert_eq!(
+            ecrecover(msg, &high_bsig, high_recid.to_i32() as u8).unwrap(),
+            pk
+        );
+
+        assert_eq!(normalize_signature(&high), sig);
+    }
+
     #[test]
     fn test_sign_verify() {
+        let msg = b"hello world";
+        let sk = PrivateKey::random();
+        let pk = sk.to_public().unwrap();
+        let sig = sign(msg, sk).unwrap();
+        assert!(verify(msg, &sig.serialize_compact().1, pk).unwrap());
+    }
+
+    #[test]
+    fn test_hashing_convention() {
         let msg = b"hello world";
         let hash = keccak256(msg);
         let sk = PrivateKey::random();
         let pk = sk.to_public().unwrap();
-        let sig = sign(&hash, sk).unwrap();
-        assert!(verify(&hash, &sig.serialize_compact().1, pk).unwrap());
+        let (recid, bsig) = sign(msg, sk).unwrap().serialize_compact();
+        let recid = recid.to_i32() as u8;
+
+        // message based functions hash `msg` themselves.
+        assert!(verify(msg, &bsig, pk).unwrap());
+        assert_eq!(
+            recover_address(msg, &bsig, recid).unwrap(),
+            Address::from_public_key(&pk)
+        );
+        assert!(recover_all(msg, &bsig).contains(&pk));
+        assert_eq!(ecrecover(msg, &bsig, recid).unwrap(), pk);
+
+        // passing the hash to a message based function hashes it twice.
+        assert!(!verify(&hash, &bsig, pk).unwrap());
+        assert_ne!(ecrecover(&hash, &bsig, recid).ok(), Some(pk));
+
+        // `_hash` functions take keccak256(msg).
+        assert!(verify_hash(&hash, &bsig, pk).unwrap());
+        assert_eq!(ecrecover_hash(&hash, &bsig, recid).unwrap(), pk);
+        assert_ne!(
+            ecrecover_hash(&keccak256(&hash), &bsig, recid).ok(),
+            Some(pk)
+        );
+    }
+
+    #[test]
+    fn test_sign_hash_verify_hash() {
+        let hash = keccak256(b"hello world");
+        let sk = PrivateKey::random();
+        let pk = sk.to_public().unwrap();
+        let (recid, bsig) = sign_hash(&hash, sk.clone()).unwrap().serialize_compact();
+        assert!(verify_hash(&hash, &bsig, pk).unwrap());
+        assert_eq!(
+            ecrecover_hash(&hash, &bsig, recid.to_i32() as u8).unwrap(),
+            pk
+        );
+
+        // `sign` hashes its input, so signing the hash again signs keccak256(hash).
+        let (_, double) = sign(&hash, sk).unwrap().serialize_compact();
+        assert!(!verify_hash(&hash, &double, pk).unwrap());
+        assert!(verify_hash(&keccak256(&hash), &double, pk).unwrap());
+    }
+
+    #[test]
+    fn test_sign_verify_with_context() {
+        let ctx = Secp256k1::new();
+        let msg = b"hello world";
+        let sk = PrivateKey::random();
+        let pk = sk.to_public().unwrap();
+        let sig = sign_with(&ctx, msg, sk).unwrap();
+        let (recid, bsig) = sig.serialize_compact();
+        assert!(verify_with(&ctx, msg, &bsig, pk).unwrap());
+        let pk2 = recover_with(&ctx, &keccak256(msg), &bsig, recid.to_i32() as u8).unwrap();
+        assert_eq!(pk, pk2);
     }
 }
@@ -0,0 +1,308 @@
+// Copyright (C) 2022 The Elabs Authors.
+// This file is part of the Elabs.
+//
+// Elabs is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Elabs is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Elabs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Ethereum transaction signing.
+
+use secp256k1::Error;
+
+use crate::{
+    rlp::{self, RlpItem},
+    *,
+};
+
+/// Legacy (pre EIP-2718) transaction, signed with EIP-155 replay protection.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LegacyTransaction {
+    /// Nonce of the sender.
+    pub nonce: u64,
+    /// Gas price in wei.
+    pub gas_price: u128,
+    /// Gas limit.
+    pub gas_limit: u64,
+    /// Recipient, `None` for contract creation.
+    pub to: Option<Address>,
+    /// Value in wei.
+    pub value: u128,
+    /// Call data or contract creation code.
+    pub data: Vec<u8>,
+    /// Chain id.
+    pub chain_id: u64,
+}
+
+impl LegacyTransaction {
+    /// Return the hash that is signed.
+    /// # Returns
+    /// keccak256(rlp([nonce, gasPrice, gasLimit, to, value, data, chainId, 0, 0])).
+    pub fn signing_hash(&self) -> [u8; 32] {
+        let mut items = self.fields();
+        items.push(RlpItem::from_u64(self.chain_id));
+        items.push(RlpItem::from_u64(0));
+        items.push(RlpItem::from_u64(0));
+        keccak256(&rlp::encode(&RlpItem::List(items)))
+    }
+
+    /// Sign the transaction with the given private key.
+    /// # Arguments
+    /// * `private_key` - The private key.
+    /// # Returns
+    /// * `Ok(Vec<u8>)` - The signed RLP encoded transaction, ready to broadcast.
+    /// * `Err(Error)` - The error that occurred, `InvalidRecoveryId` if the EIP-155 `v`
+    ///   of `chain_id` does not fit in a u64.
+    pub fn sign(&self, private_key: PrivateKey) -> Result<Vec<u8>, Error> {
+        let (recid, bsig) = sign_hash(&self.signing_hash(), private_key)?.serialize_compact();
+        let recid = recid.to_i32() as u8;
+        let mut items = self.fields();
+        items.push(RlpItem::from_u64(apply_v(recid, Some(self.chain_id))?));
+        items.push(RlpItem::from_uint(&bsig[..32]));
+        items.push(RlpItem::from_uint(&bsig[32..]));
+        Ok(rlp::encode(&RlpItem::List(items)))
+    }
+
+    fn fields(&self) -> Vec<RlpItem> {
+        vec![
+            RlpItem::from_u64(self.nonce),
+            RlpItem::from_uint(&self.gas_price.to_be_bytes()),
+            RlpItem::from_u64(self.gas_limit),
+            RlpItem::Bytes(self.to.map(|a| a.0.to_vec()).unwrap_or_default()),
+            RlpItem::from_uint(&self.value.to_be_bytes()),
+            RlpItem::Bytes(self.data.clone()),
+        ]
+    }
+}
+
+/// EIP-2718 type byte of EIP-1559 transactions.
+pub const EIP1559_TX_TYPE: u8 = 0x02;
+
+/// Entry of an EIP-2930 access list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccessListItem {
+    /// Accessed contract.
+    pub address: Address,
+    /// Accessed storage slots of the contract.
+    pub storage_keys: Vec<[u8; 32]>,
+}
+
+/// EIP-1559 dynamic fee transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Eip1559Transaction {
+    /// Chain id.
+    pub chain_id: u64,
+    /// Nonce of the sender.
+    pub nonce: u64,
+    /// Priority fee (tip) per gas in wei.
+    pub max_priority_fee_per_gas: u128,
+    /// Maximum total fee per gas in wei.
+    pub max_fee_per_gas: u128,
+    /// Gas limit.
+    pub gas_limit: u64,
+    /// Recipient, `None` for contract creation.
+    pub to: Option<Address>,
+    /// Value in wei.
+    pub value: u128,
+    /// Call data or contract creation code.
+    pub data: Vec<u8>,
+    /// Addresses and storage slots pre-warmed by the transaction.
+    pub access_list: Vec<AccessListItem>,
+}
+
+impl Eip1559Transaction {
+    /// Return the hash that is signed.
+    /// # Returns
+    /// keccak256(0x02 ++ rlp([chainId, nonce, maxPriorityFeePerGas, maxFeePerGas, gasLimit, to, value, data, accessList])).
+    pub fn signing_hash(&self) -> [u8; 32] {
+        keccak256(&self.encode(self.fields()))
+    }
+
+    /// Sign the transaction with the given private key.
+    /// # Arguments
+    /// * `private_key` - The private key.
+    /// # Returns
+    /// * `Ok(Vec<u8>)` - The signed transaction `0x02 ++ rlp(..)`, ready to broadcast.
+    /// * `Err(Error)` - The error that occurred.
+    pub fn sign(&self, private_key: PrivateKey) -> Result<Vec<u8>, Error> {
+        let (recid, bsig) = sign_hash(&self.signing_hash(), private_key)?.serialize_compact();
+        let recid = recid.to_i32() as u8;
+        Ok(self.encode_signed(recid, &bsig))
+    }
+
+    fn encode_signed(&self, y_parity: u8, signature: &[u8; 64]) -> Vec<u8> {
+        let mut items = self.fields();
+        items.push(RlpItem::from_u64(y_parity as u64));
+        items.push(RlpItem::from_uint(&signature[..32]));
+        items.push(RlpItem::from_uint(&signature[32..]));
+        self.encode(items)
+    }
+
+    fn encode(&self, items: Vec<RlpItem>) -> Vec<u8> {
+        let mut out = vec![EIP1559_TX_TYPE];
+        out.extend(rlp::encode(&RlpItem::List(items)));
+        out
+    }
+
+    fn fields(&self) -> Vec<RlpItem> {
+        let access_list = self
+            .access_list
+            .iter()
+            .map(|item| {
+                RlpItem::List(vec![
+                    RlpItem::Bytes(item.address.0.to_vec()),
+                    RlpItem::List(
+                        item.storage_keys
+                            .iter()
+                            .map(|key| RlpItem::Bytes(key.to_vec()))
+                            .collect(),
+                    ),
+                ])
+            })
+            .collect();
+        vec![
+            RlpItem::from_u64(self.chain_id),
+            RlpItem::from_u64(self.nonce),
+            RlpItem::from_uint(&self.max_priority_fee_per_gas.to_be_bytes()),
+            RlpItem::from_uint(&self.max_fee_per_gas.to_be_bytes()),
+            RlpItem::from_u64(self.gas_limit),
+            RlpItem::Bytes(self.to.map(|a| a.0.to_vec()).unwrap_or_default()),
+            RlpItem::from_uint(&self.value.to_be_bytes()),
+            RlpItem::Bytes(self.data.clone()),
+            RlpItem::List(access_list),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Example from EIP-155.
+    fn eip155_example() -> LegacyTransaction {
+        LegacyTransaction {
+            nonce: 9,
+            gas_price: 20_000_000_000,
+            gas_limit: 21000,
+            to: Some(Address([0x35; 20])),
+            value: 1_000_000_000_000_000_000,
+            data: vec![],
+            chain_id: 1,
+        }
+    }
+
+    #[test]
+    fn test_legacy_signing_hash() {
+        assert_eq!(
+            hex::encode(eip155_example().signing_hash()),
+            "daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53"
+        );
+    }
+
+    #[test]
+    fn test_legacy_sign() {
+        let sk = PrivateKey([0x46; 32]);
+        let signed = eip155_example().sign(sk).unwrap();
+        assert_eq!(
+            hex::encode(signed),
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000\
+             8025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d899\
+             7f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+    }
+
+    #[test]
+    fn test_legacy_sign_contract_creation() {
+        let sk = PrivateKey::random();
+        let tx = LegacyTransaction {
+            to: None,
+            data: vec![0x60, 0x00],
+            ..eip155_example()
+        };
+        let signed = rlp::decode(&tx.sign(sk).unwrap()).unwrap();
+        let items = signed.as_list().unwrap();
+        assert_eq!(items.len(), 9);
+        assert_eq!(items[3], RlpItem::Bytes(vec![]));
+    }
+
+    #[test]
+    fn test_legacy_sign_chain_id_overflow() {
+        let tx = LegacyTransaction {
+            chain_id: u64::MAX / 2,
+            ..eip155_example()
+        };
+        assert_eq!(
+            tx.sign(PrivateKey([0x46; 32])),
+            Err(Error::InvalidRecoveryId)
+        );
+    }
+
+    // Mainnet transaction 0x86718885c4b4218c6af87d3d0b0d83e3cc465df2a05c048aa4db9f1a6f9de91f.
+    const EIP1559_SIGNED: &str = "02f872018307910d808507204d2cb1827d0094388c818ca8b9251b393131c08a736a67ccb19297880320d04823e2701c80c001a0cf024f4815304df2867a1a74e9d2707b6abda0337d2d54a4438d453f4160f190a07ac0e6b3bc9395b5b9c8b9e6d77204a236577a5b18467b9175c01de4faa208d9";
+
+    fn eip1559_example() -> Eip1559Transaction {
+        Eip1559Transaction {
+            chain_id: 1,
+            nonce: 0x07910d,
+            max_priority_fee_per_gas: 0,
+            max_fee_per_gas: 0x07204d2cb1,
+            gas_limit: 32000,
+            to: Some(Address::from_hex("0x388c818ca8b9251b393131c08a736a67ccb19297").unwrap()),
+            value: 0x0320d04823e2701c,
+            data: vec![],
+            access_list: vec![],
+        }
+    }
+
+    #[test]
+    fn test_eip1559_encode_signed() {
+        let signed = hex::decode(EIP1559_SIGNED).unwrap();
+        let tx = eip1559_example();
+        let decoded = rlp::decode(&signed[1..]).unwrap();
+        let items = decoded.as_list().unwrap();
+        let mut bsig = [0u8; 64];
+        bsig[..32].copy_from_slice(&items[10].as_u256().unwrap());
+        bsig[32..].copy_from_slice(&items[11].as_u256().unwrap());
+        assert_eq!(tx.encode_signed(1, &bsig), signed);
+
+        let signer =
+            Address::from_public_key(&ecrecover_hash(&tx.signing_hash(), &bsig, 1).unwrap());
+        assert_eq!(
+            signer.to_hex(),
+            "0x95222290DD7278Aa3Ddd389Cc1E1d165CC4BAfe5"
+        );
+    }
+
+    #[test]
+    fn test_eip1559_sign() {
+        let sk = PrivateKey::random();
+        let tx = Eip1559Transaction {
+            access_list: vec![AccessListItem {
+                address: Address([0x11; 20]),
+                storage_keys: vec![[0x22; 32]],
+            }],
+            ..eip1559_example()
+        };
+        let signed = tx.sign(sk.clone()).unwrap();
+        assert_eq!(signed[0], EIP1559_TX_TYPE);
+
+        let decoded = rlp::decode(&signed[1..]).unwrap();
+        let items = decoded.as_list().unwrap();
+        assert_eq!(items.len(), 12);
+        let y_parity = items[9].as_u64().unwrap() as u8;
+        let mut bsig = items[10].as_u256().unwrap().to_vec();
+        bsig.extend(items[11].as_u256().unwrap());
+        assert_eq!(
+            ecrecover_hash(&tx.signing_hash(), &bsig, y_parity).unwrap(),
+            sk.to_public().unwrap()
+        );
+    }
+}